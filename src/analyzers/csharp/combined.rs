@@ -0,0 +1,334 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::io::Error;
+
+use crate::config::types::Config;
+use crate::core::analysis::GraphDependencies;
+use crate::core::dependencies::{NodeDependencies, SourceLocation};
+use crate::core::node::Node;
+use crate::analyzers::csharp::namespace::{NamespaceDependencyManager, NamespaceScan};
+use crate::analyzers::csharp::project::{ProjectDependencyManager, PROJECT_NODE_TYPE};
+
+/// Project and namespace dependencies in one graph. Project nodes come first, followed by the
+/// namespace nodes; a namespace declared in an analyzed project has the id of that project as
+/// group.
+pub struct CombinedDependencyManager;
+
+/// A namespace dependency between two projects that the using project does not reference
+/// directly: the code compiles only thanks to a transitive reference, or not at all.
+#[derive(Debug, Clone)]
+pub struct ReferenceLeak {
+    /// Namespace nodes of the dependency.
+    pub from: usize,
+    pub to: usize,
+    /// Project nodes owning them.
+    pub from_project: usize,
+    pub to_project: usize,
+    /// The using project reaches the other one through other project references.
+    pub transitive: bool,
+}
+
+/// A project whose namespaces are used by another project, without a direct `ProjectReference`.
+#[derive(Debug, Clone)]
+pub struct UndeclaredReference {
+    pub from_project: usize,
+    pub to_project: usize,
+    pub transitive: bool,
+    /// Namespace dependencies (from, to) behind it.
+    pub namespaces: Vec<(usize, usize)>,
+}
+
+/// Project references compared with the namespace dependencies of the code.
+#[derive(Debug, Clone)]
+pub struct ReferenceReport {
+    pub undeclared: Vec<UndeclaredReference>,
+    pub unused: Vec<UnusedReference>,
+}
+
+/// A `ProjectReference` whose target declares no namespace or type that the referencing
+/// project uses.
+#[derive(Debug, Clone)]
+pub struct UnusedReference {
+    pub from: usize,
+    pub to: usize,
+    /// The `ProjectReference` items declaring the reference.
+    pub locations: Vec<SourceLocation>,
+}
+
+impl CombinedDependencyManager {
+    /// Appends the namespace nodes to the given project nodes.
+    pub fn add_namespace_nodes(mut nodes: Vec<Node>, scan: &NamespaceScan, config: &Config) -> Result<Vec<Node>, Error> {
+        let owners = NamespaceDependencyManager::namespace_owners(scan);
+        let project_ids: HashSet<String> = nodes.iter().map(|node| node.id.clone()).collect();
+
+        let mut namespaces = NamespaceDependencyManager::collect_nodes(scan, config)?;
+        namespaces.sort_by(|a, b| a.id.cmp(&b.id));
+        for mut namespace in namespaces {
+            // Projects left out of the analysis (solution filter, exclusions) do not own namespaces
+            namespace.group = owners.get(&namespace.id).filter(|project| project_ids.contains(*project)).cloned();
+            nodes.push(namespace);
+        }
+
+        Ok(nodes)
+    }
+
+    pub fn find_dependencies(scan: &NamespaceScan, nodes: &[Node], config: &Config) -> Result<NodeDependencies, Error> {
+        let project_count = project_count(nodes);
+        let mut node_dependencies = ProjectDependencyManager::find_dependencies(&nodes[..project_count], config)?;
+        let namespace_dependencies = NamespaceDependencyManager::find_dependencies(scan, &nodes[project_count..], config)?;
+        for mut edges in namespace_dependencies {
+            for edge in &mut edges {
+                edge.to += project_count;
+            }
+            node_dependencies.push(edges);
+        }
+
+        Ok(node_dependencies)
+    }
+
+    /// Namespace dependencies that cross projects without a matching `ProjectReference`.
+    pub fn find_reference_leaks(nodes: &[Node], node_dependencies: &NodeDependencies) -> Vec<ReferenceLeak> {
+        let project_count = project_count(nodes);
+        let project_index: HashMap<&str, usize> = nodes[..project_count].iter().enumerate()
+            .map(|(index, node)| (node.id.as_str(), index))
+            .collect();
+        let owner = |index: usize| nodes[index].group.as_deref().and_then(|group| project_index.get(group).copied());
+
+        let mut leaks = Vec::new();
+        for (from, edges) in node_dependencies.iter().enumerate().skip(project_count) {
+            let Some(from_project) = owner(from) else { continue };
+            for edge in edges {
+                let Some(to_project) = owner(edge.to) else { continue };
+                if to_project == from_project || node_dependencies[from_project].iter().any(|e| e.to == to_project) {
+                    continue;
+                }
+                let transitive = reaches(node_dependencies, from_project, to_project, project_count);
+                leaks.push(ReferenceLeak { from, to: edge.to, from_project, to_project, transitive });
+            }
+        }
+
+        leaks
+    }
+
+    /// Groups the reference leaks by project pair and finds the unused project references.
+    pub fn reference_report(scan: &NamespaceScan, nodes: &[Node], node_dependencies: &NodeDependencies) -> ReferenceReport {
+        let mut report = ReferenceReport { undeclared: Vec::new(), unused: Self::find_unused_references(scan, nodes, node_dependencies) };
+
+        for leak in Self::find_reference_leaks(nodes, node_dependencies) {
+            let pair = (leak.from_project, leak.to_project);
+            match report.undeclared.iter_mut().find(|undeclared| (undeclared.from_project, undeclared.to_project) == pair) {
+                Some(undeclared) => undeclared.namespaces.push((leak.from, leak.to)),
+                None => report.undeclared.push(UndeclaredReference {
+                    from_project: leak.from_project,
+                    to_project: leak.to_project,
+                    transitive: leak.transitive,
+                    namespaces: vec![(leak.from, leak.to)],
+                }),
+            }
+        }
+
+        report
+    }
+
+    /// Project references whose target declares namespaces, none of which the referencing
+    /// project's code uses through a `using` directive or a type reference. Projects without
+    /// source files are skipped on both sides: there is nothing to tell whether their code is used.
+    pub fn find_unused_references(scan: &NamespaceScan, nodes: &[Node], node_dependencies: &NodeDependencies) -> Vec<UnusedReference> {
+        let project_count = project_count(nodes);
+        let project_namespaces = NamespaceDependencyManager::project_namespaces(scan);
+
+        let mut unused = Vec::new();
+        for (from, edges) in node_dependencies.iter().enumerate().take(project_count) {
+            let Some(from_namespaces) = project_namespaces.get(&nodes[from].id) else { continue };
+            for edge in edges.iter().filter(|edge| edge.to < project_count) {
+                let Some(to_namespaces) = project_namespaces.get(&nodes[edge.to].id) else { continue };
+                if to_namespaces.declared.is_empty() || !to_namespaces.declared.is_disjoint(&from_namespaces.used) {
+                    continue;
+                }
+                unused.push(UnusedReference { from, to: edge.to, locations: edge.locations.clone() });
+            }
+        }
+
+        unused
+    }
+}
+
+fn project_count(nodes: &[Node]) -> usize {
+    nodes.iter().take_while(|node| node.node_type == PROJECT_NODE_TYPE).count()
+}
+
+// Whether `to` can be reached from `from` following project references only
+fn reaches(node_dependencies: &NodeDependencies, from: usize, to: usize, project_count: usize) -> bool {
+    let mut visited = vec![false; project_count];
+    let mut queue = VecDeque::from([from]);
+    visited[from] = true;
+    while let Some(current) = queue.pop_front() {
+        for edge in node_dependencies[current].iter().filter(|edge| edge.to < project_count) {
+            if edge.to == to {
+                return true;
+            }
+            if !visited[edge.to] {
+                visited[edge.to] = true;
+                queue.push_back(edge.to);
+            }
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::Path;
+    use crate::analyzers::csharp::msbuild::test_fixture;
+    use crate::output::static_output::display_fix_suggestions;
+
+    const ENTITIES: (&str, &str) = ("Shop.Entities/Shop.Entities.csproj", "<Project Sdk=\"Microsoft.NET.Sdk\" />");
+    const ORDER: (&str, &str) = ("Shop.Entities/Order.cs", "namespace Shop.Entities { public class Order { } }");
+    const USE_CASES: (&str, &str) = ("Shop.UseCases/Shop.UseCases.csproj", r#"<Project Sdk="Microsoft.NET.Sdk">
+  <ItemGroup><ProjectReference Include="../Shop.Entities/Shop.Entities.csproj" /></ItemGroup>
+</Project>"#);
+    // The use case exposes the entity in its public API
+    const PLACE_ORDER: (&str, &str) = ("Shop.UseCases/PlaceOrder.cs", "using Shop.Entities;
+namespace Shop.UseCases { public class PlaceOrder { public Order Run() { return new Order(); } } }");
+
+    // Fixture names must not match the default exclusions (`bin`, `obj`)
+    fn analyze(root: &Path) -> (NamespaceScan, Vec<Node>, NodeDependencies) {
+        let config = Config::default();
+        let projects = ProjectDependencyManager::collect_nodes(root, &config).unwrap();
+        let scan = NamespaceDependencyManager::scan(root, &config).unwrap();
+        let nodes = CombinedDependencyManager::add_namespace_nodes(projects, &scan, &config).unwrap();
+        let node_dependencies = CombinedDependencyManager::find_dependencies(&scan, &nodes, &config).unwrap();
+        (scan, nodes, node_dependencies)
+    }
+
+    fn index(nodes: &[Node], name: &str) -> usize {
+        nodes.iter().position(|node| node.name == name).unwrap()
+    }
+
+    #[test]
+    fn leaks_are_namespaces_of_projects_not_referenced_directly() {
+        let root = test_fixture("reference-leaks", &[
+            ENTITIES, ORDER, USE_CASES, PLACE_ORDER,
+            ("Shop.IO/Shop.IO.csproj", r#"<Project Sdk="Microsoft.NET.Sdk">
+  <ItemGroup><ProjectReference Include="../Shop.UseCases/Shop.UseCases.csproj" /></ItemGroup>
+</Project>"#),
+            // Names the entity returned by the use case: needs Shop.Entities, reached transitively
+            ("Shop.IO/OrderStore.cs", "using Shop.Entities;
+using Shop.UseCases;
+namespace Shop.IO { class OrderStore { Order Save() { return new PlaceOrder().Run(); } } }"),
+            ("Shop.Web/Shop.Web.csproj", r#"<Project Sdk="Microsoft.NET.Sdk">
+  <ItemGroup><ProjectReference Include="../Shop.UseCases/Shop.UseCases.csproj" /></ItemGroup>
+</Project>"#),
+            // Only uses the use case; the entity stays internal to the call
+            ("Shop.Web/Page.cs", "using Shop.UseCases;
+namespace Shop.Web { class Page { void Post() { var order = new PlaceOrder().Run(); } } }"),
+        ]);
+        let (_, nodes, node_dependencies) = analyze(&root);
+        let leaks = CombinedDependencyManager::find_reference_leaks(&nodes, &node_dependencies);
+
+        assert_eq!(leaks.len(), 1, "{:?}", leaks);
+        let leak = &leaks[0];
+        assert_eq!((leak.from, leak.to), (index(&nodes, "Shop.IO"), index(&nodes, "Shop.Entities")));
+        assert_eq!((leak.from_project, leak.to_project), (index(&nodes, "Shop.IO.csproj"), index(&nodes, "Shop.Entities.csproj")));
+        assert!(leak.transitive);
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn leaks_without_any_path_between_the_projects_are_not_transitive() {
+        let root = test_fixture("untransitive-leaks", &[
+            ENTITIES, ORDER,
+            ("Shop.IO/Shop.IO.csproj", "<Project Sdk=\"Microsoft.NET.Sdk\" />"),
+            ("Shop.IO/OrderStore.cs", "using Shop.Entities;
+namespace Shop.IO { class OrderStore { } }"),
+        ]);
+        let (_, nodes, node_dependencies) = analyze(&root);
+        let leaks = CombinedDependencyManager::find_reference_leaks(&nodes, &node_dependencies);
+
+        assert_eq!(leaks.len(), 1, "{:?}", leaks);
+        assert!(!leaks[0].transitive);
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn unused_references_skip_global_and_implicit_usings() {
+        let reference = r#"<ItemGroup><ProjectReference Include="../Shop.Entities/Shop.Entities.csproj" /></ItemGroup>"#;
+        let unused_project = format!("<Project Sdk=\"Microsoft.NET.Sdk\">\n  {}\n</Project>", reference);
+        let implicit_project = format!("<Project Sdk=\"Microsoft.NET.Sdk\">\n  {}\n  <ItemGroup><Using Include=\"Shop.Entities\" /></ItemGroup>\n</Project>", reference);
+        let root = test_fixture("unused-references", &[
+            ENTITIES, ORDER,
+            ("Shop.IO/Shop.IO.csproj", &unused_project),
+            ("Shop.IO/OrderStore.cs", "namespace Shop.IO { class OrderStore { } }"),
+            ("Shop.Web/Shop.Web.csproj", &unused_project),
+            ("Shop.Web/Usings.cs", "global using Shop.Entities;"),
+            ("Shop.Web/Page.cs", "namespace Shop.Web { class Page { } }"),
+            ("Shop.Api/Shop.Api.csproj", &implicit_project),
+            ("Shop.Api/Endpoint.cs", "namespace Shop.Api { class Endpoint { } }"),
+        ]);
+        let (scan, nodes, node_dependencies) = analyze(&root);
+        let unused = CombinedDependencyManager::find_unused_references(&scan, &nodes, &node_dependencies);
+
+        assert_eq!(unused.len(), 1, "{:?}", unused);
+        assert_eq!((unused[0].from, unused[0].to), (index(&nodes, "Shop.IO.csproj"), index(&nodes, "Shop.Entities.csproj")));
+        assert_eq!(unused[0].locations.len(), 1);
+        assert_eq!(unused[0].locations[0].file, root.join("Shop.IO/Shop.IO.csproj").to_string_lossy());
+        assert_eq!(unused[0].locations[0].line, 2);
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn reference_report_groups_leaks_by_project_and_suggests_fixes() {
+        let root = test_fixture("reference-report", &[
+            ENTITIES, ORDER, USE_CASES, PLACE_ORDER,
+            ("Shop.IO/Shop.IO.csproj", r#"<Project Sdk="Microsoft.NET.Sdk">
+  <ItemGroup><ProjectReference Include="../Shop.UseCases/Shop.UseCases.csproj" /></ItemGroup>
+</Project>"#),
+            ("Shop.IO/OrderStore.cs", "using Shop.Entities;
+using Shop.UseCases;
+namespace Shop.IO { class OrderStore { } }
+namespace Shop.IO.Db { using Shop.Entities; class OrderTable { } }"),
+            ("Shop.Web/Shop.Web.csproj", r#"<Project Sdk="Microsoft.NET.Sdk">
+  <ItemGroup>
+    <ProjectReference Include="../Shop.Entities/Shop.Entities.csproj">
+      <Private>false</Private>
+    </ProjectReference>
+  </ItemGroup>
+</Project>"#),
+            ("Shop.Web/Page.cs", "namespace Shop.Web { class Page { } }"),
+            ("Shop.Api/Directory.Build.props", r#"<Project>
+  <ItemGroup><ProjectReference Include="../Shop.Entities/Shop.Entities.csproj" /></ItemGroup>
+</Project>"#),
+            ("Shop.Api/Shop.Api.csproj", "<Project Sdk=\"Microsoft.NET.Sdk\" />"),
+            ("Shop.Api/Endpoint.cs", "namespace Shop.Api { class Endpoint { } }"),
+        ]);
+        let (scan, nodes, node_dependencies) = analyze(&root);
+        let report = CombinedDependencyManager::reference_report(&scan, &nodes, &node_dependencies);
+
+        assert_eq!(report.undeclared.len(), 1, "{:?}", report.undeclared);
+        let undeclared = &report.undeclared[0];
+        assert_eq!((undeclared.from_project, undeclared.to_project), (index(&nodes, "Shop.IO.csproj"), index(&nodes, "Shop.Entities.csproj")));
+        assert!(undeclared.transitive);
+        let mut namespaces = undeclared.namespaces.clone();
+        namespaces.sort();
+        assert_eq!(namespaces, [(index(&nodes, "Shop.IO"), index(&nodes, "Shop.Entities")), (index(&nodes, "Shop.IO.Db"), index(&nodes, "Shop.Entities"))]);
+
+        let mut unused: Vec<_> = report.unused.iter().map(|reference| (reference.from, reference.to)).collect();
+        unused.sort();
+        let mut expected = [(index(&nodes, "Shop.Api.csproj"), index(&nodes, "Shop.Entities.csproj")), (index(&nodes, "Shop.Web.csproj"), index(&nodes, "Shop.Entities.csproj"))];
+        expected.sort();
+        assert_eq!(unused, expected);
+
+        let mut out = Vec::new();
+        display_fix_suggestions(&mut out, &nodes, &report.unused).unwrap();
+        let expected = format!("
+Suggested fixes: delete these lines
+{root}/Shop.Api/Directory.Build.props
+  line 2: <ProjectReference Include=\"../Shop.Entities/Shop.Entities.csproj\" /> (Shop.Api.csproj -> Shop.Entities.csproj, shared with the other projects importing this file)
+{root}/Shop.Web/Shop.Web.csproj
+  lines 3-5: <ProjectReference Include=\"../Shop.Entities/Shop.Entities.csproj\"> <Private>false</Private> </ProjectReference> (Shop.Web.csproj -> Shop.Entities.csproj)
+", root = root.display());
+        assert_eq!(String::from_utf8(out).unwrap(), expected);
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -1,2 +1,8 @@
 pub mod project;
 pub mod namespace;
+pub mod package;
+pub mod combined;
+pub mod msbuild;
+pub mod solution;
+pub mod tokenizer;
+pub mod syntax;
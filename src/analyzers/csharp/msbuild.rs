@@ -0,0 +1,1000 @@
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fs::File;
+use std::io::{Error, Read};
+use std::path::{Path, PathBuf, MAIN_SEPARATOR};
+use xml::common::Position;
+use xml::reader::{EventReader, XmlEvent};
+
+use crate::core::dependencies::SourceLocation;
+use crate::utils::strings::RemoveBom;
+
+const DIRECTORY_BUILD_PROPS: &str = "Directory.Build.props";
+const DIRECTORY_BUILD_TARGETS: &str = "Directory.Build.targets";
+const DIRECTORY_PACKAGES_PROPS: &str = "Directory.Packages.props";
+
+/// An item (`ProjectReference`, `PackageReference`, `PackageVersion`...) declared in an
+/// `ItemGroup` of an MSBuild file.
+#[derive(Debug, Clone)]
+pub struct MsBuildItem {
+    pub item_type: String,
+    pub include: Option<String>,
+    /// Attributes other than `Include` and `Condition`, plus child elements such as `<Version>`.
+    pub metadata: HashMap<String, String>,
+    /// Conditions of the enclosing `ItemGroup` and of the item itself.
+    pub conditions: Vec<String>,
+    /// 1-based line and column of the item element.
+    pub line: usize,
+    pub column: usize,
+    /// Line of the end of the element: its closing tag, or the end of the empty element.
+    pub end_line: usize,
+    /// The item element as written, whitespace collapsed.
+    pub text: String,
+}
+
+/// A property set in a `PropertyGroup`.
+#[derive(Debug, Clone)]
+pub struct MsBuildProperty {
+    pub name: String,
+    pub value: String,
+    pub conditions: Vec<String>,
+    pub line: usize,
+    pub column: usize,
+    pub end_line: usize,
+    /// The property element as written, whitespace collapsed.
+    pub text: String,
+}
+
+/// An `<Import Project="..."/>` element.
+#[derive(Debug, Clone)]
+pub struct MsBuildImport {
+    pub project: String,
+    pub sdk: Option<String>,
+    pub conditions: Vec<String>,
+    pub line: usize,
+}
+
+#[derive(Debug, Clone)]
+pub enum MsBuildElement {
+    Property(MsBuildProperty),
+    Import(MsBuildImport),
+    Item(MsBuildItem),
+}
+
+/// The parts of a project, props or targets file that matter for dependency analysis, in
+/// document order.
+#[derive(Debug, Clone)]
+pub struct MsBuildFile {
+    pub path: PathBuf,
+    pub contents: String,
+    /// `Sdk` attribute of the root `<Project>` element.
+    pub sdk: Option<String>,
+    pub elements: Vec<MsBuildElement>,
+}
+
+impl MsBuildFile {
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let mut file = File::open(path)?;
+        let mut contents = String::new();
+        file.read_to_string(&mut contents)?;
+        contents = contents.remove_bom();
+        Self::parse(path, contents)
+    }
+
+    pub fn parse(path: &Path, contents: String) -> Result<Self, Error> {
+        let mut elements = Vec::new();
+        let mut sdk = None;
+        // Names of the currently open elements, root first
+        let mut stack: Vec<String> = Vec::new();
+        let mut group_condition: Option<String> = None;
+        let mut current_item: Option<MsBuildItem> = None;
+        let mut current_property: Option<MsBuildProperty> = None;
+        let mut text = String::new();
+
+        let mut reader = EventReader::from_str(&contents);
+        loop {
+            let event = reader.next().map_err(Error::other)?;
+            let line = reader.position().row as usize + 1;
+            let column = reader.position().column as usize + 1;
+            match event {
+                XmlEvent::StartElement { name, attributes, .. } => {
+                    let element = name.local_name;
+                    let parent = stack.last().map(String::as_str);
+                    let attribute = |key: &str| attributes.iter()
+                        .find(|a| a.name.local_name == key)
+                        .map(|a| a.value.clone());
+                    let conditions = || group_condition.iter().cloned()
+                        .chain(attribute("Condition"))
+                        .collect::<Vec<_>>();
+                    match (stack.len(), parent) {
+                        (0, _) => sdk = attribute("Sdk"),
+                        (1, _) if matches!(element.as_str(), "ItemGroup" | "PropertyGroup" | "ImportGroup") => {
+                            group_condition = attribute("Condition");
+                        }
+                        (1, _) | (2, Some("ImportGroup")) if element == "Import" => {
+                            if let Some(project) = attribute("Project") {
+                                elements.push(MsBuildElement::Import(MsBuildImport {
+                                    project,
+                                    sdk: attribute("Sdk"),
+                                    conditions: conditions(),
+                                    line,
+                                }));
+                            }
+                        }
+                        (2, Some("ItemGroup")) => {
+                            let (text, end_line) = element_source(&contents, line, column, &element);
+                            current_item = Some(MsBuildItem {
+                                item_type: element.clone(),
+                                include: attribute("Include"),
+                                metadata: attributes.iter()
+                                    .filter(|a| a.name.local_name != "Include" && a.name.local_name != "Condition")
+                                    .map(|a| (a.name.local_name.clone(), a.value.clone()))
+                                    .collect(),
+                                conditions: conditions(),
+                                line,
+                                column,
+                                end_line,
+                                text,
+                            });
+                        }
+                        (2, Some("PropertyGroup")) => {
+                            let (text, end_line) = element_source(&contents, line, column, &element);
+                            current_property = Some(MsBuildProperty {
+                                name: element.clone(),
+                                value: String::new(),
+                                conditions: conditions(),
+                                line,
+                                column,
+                                end_line,
+                                text,
+                            });
+                        }
+                        _ => {}
+                    }
+                    text.clear();
+                    stack.push(element);
+                }
+                XmlEvent::Characters(value) | XmlEvent::CData(value) => text.push_str(&value),
+                XmlEvent::EndElement { .. } => {
+                    let element = stack.pop().unwrap_or_default();
+                    match stack.len() {
+                        // Child element of an item: metadata such as <Version>1.0</Version>
+                        3 if stack[1] == "ItemGroup" => {
+                            if let Some(item) = current_item.as_mut() {
+                                item.metadata.insert(element, text.trim().to_string());
+                            }
+                        }
+                        2 if stack[1] == "ItemGroup" => {
+                            if let Some(item) = current_item.take() {
+                                elements.push(MsBuildElement::Item(item));
+                            }
+                        }
+                        2 if stack[1] == "PropertyGroup" => {
+                            if let Some(mut property) = current_property.take() {
+                                property.value = text.trim().to_string();
+                                elements.push(MsBuildElement::Property(property));
+                            }
+                        }
+                        1 => group_condition = None,
+                        _ => {}
+                    }
+                    text.clear();
+                }
+                XmlEvent::EndDocument => break,
+                _ => {}
+            }
+        }
+
+        Ok(MsBuildFile { path: path.to_path_buf(), contents, sdk, elements })
+    }
+}
+
+// Source of the element starting at `line` and `column` (1-based, in characters), up to the end
+// of the empty element or of its closing tag, with runs of whitespace collapsed, and the line
+// where it ends
+fn element_source(contents: &str, line: usize, column: usize, element: &str) -> (String, usize) {
+    let Some(start_line) = contents.split('\n').nth(line - 1) else { return (String::new(), line) };
+    let line_offset = start_line.as_ptr() as usize - contents.as_ptr() as usize;
+    let start = line_offset + start_line.char_indices().nth(column - 1).map_or(0, |(index, _)| index);
+    let rest = &contents[start..];
+
+    // End of the start tag, skipping '>' inside attribute values
+    let mut quote = None;
+    let mut tag_end = None;
+    for (index, c) in rest.char_indices() {
+        match (quote, c) {
+            (Some(q), _) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '>') => {
+                tag_end = Some(index + 1);
+                break;
+            }
+            _ => {}
+        }
+    }
+    let Some(tag_end) = tag_end else { return (String::new(), line) };
+    let end = if rest[..tag_end].ends_with("/>") {
+        tag_end
+    } else {
+        let closing = format!("</{}", element);
+        rest[tag_end..].find(&closing)
+            .and_then(|index| rest[tag_end + index..].find('>').map(|end| tag_end + index + end + 1))
+            .unwrap_or(tag_end)
+    };
+    let source = &rest[..end];
+    (source.split_whitespace().collect::<Vec<_>>().join(" "), line + source.matches('\n').count())
+}
+
+/// An item whose conditions hold, with properties expanded.
+#[derive(Debug, Clone)]
+pub struct EvaluatedItem {
+    pub item_type: String,
+    /// `Include` as written in the file.
+    pub raw_include: Option<String>,
+    /// `Include` with properties expanded. Expressions that cannot be evaluated are kept as
+    /// written, so the value will not resolve to a file.
+    pub include: Option<String>,
+    pub metadata: HashMap<String, String>,
+    /// File that declared the item.
+    pub file: PathBuf,
+    pub line: usize,
+    pub column: usize,
+    pub end_line: usize,
+    /// The item element as written, whitespace collapsed.
+    pub text: String,
+}
+
+impl EvaluatedItem {
+    pub fn metadata(&self, name: &str) -> Option<&str> {
+        self.metadata.get(name).map(String::as_str)
+    }
+}
+
+/// Where an item is declared, for dependencies coming from it.
+pub fn item_location(item: &EvaluatedItem, kind: &str) -> SourceLocation {
+    SourceLocation {
+        file: item.file.to_string_lossy().to_string(),
+        line: item.line,
+        column: item.column,
+        end_line: item.end_line,
+        kind: kind.to_string(),
+        text: item.text.clone(),
+    }
+}
+
+/// A project file evaluated together with every MSBuild file it pulls in: the nearest
+/// `Directory.Build.props`, `Directory.Packages.props` and `Directory.Build.targets`, and the
+/// files referenced by `<Import>` elements (recursively).
+///
+/// As in MSBuild, properties and imports are evaluated first in document order, then items
+/// are evaluated with the final property values.
+#[derive(Debug, Clone)]
+pub struct EvaluatedProject {
+    pub project_path: PathBuf,
+    pub sdk: Option<String>,
+    /// Final property values, keyed by lowercase name.
+    pub properties: HashMap<String, String>,
+    /// File and element of the last definition of each property set by a project file.
+    pub property_definitions: HashMap<String, (PathBuf, MsBuildProperty)>,
+    pub items: Vec<EvaluatedItem>,
+    /// Imports that were not found or could not be read, expressions or conditions that could
+    /// not be evaluated.
+    pub warnings: Vec<String>,
+}
+
+impl EvaluatedProject {
+    /// Evaluates a project. `global_properties` behave like `-p:Name=Value` on the MSBuild
+    /// command line: the project files cannot override them. Fails only when the project file
+    /// itself cannot be read.
+    pub fn load(project_path: &Path, global_properties: &HashMap<String, String>) -> Result<Self, Error> {
+        let project_dir = project_path.parent().unwrap_or(Path::new("."));
+        let mut evaluator = Evaluator::new(project_path, global_properties);
+        let mut visited: HashSet<PathBuf> = HashSet::new();
+        let mut pending_items: Vec<(PathBuf, MsBuildItem)> = Vec::new();
+
+        // Directory.*.props are imported by the SDK before the project body, the targets after it
+        let mut files: Vec<PathBuf> = [DIRECTORY_BUILD_PROPS, DIRECTORY_PACKAGES_PROPS].iter()
+            .filter_map(|name| find_file_above(project_dir, name))
+            .collect();
+        files.push(project_path.to_path_buf());
+        files.extend(find_file_above(project_dir, DIRECTORY_BUILD_TARGETS));
+        for file in &files {
+            evaluator.evaluate_file(file, &mut visited, &mut pending_items)?;
+        }
+
+        let mut items = Vec::new();
+        for (file, item) in pending_items {
+            if !evaluator.conditions_hold(&item.conditions, &file, item.line) {
+                continue;
+            }
+            let include = item.include.as_deref()
+                .map(|value| evaluator.expand_or_warn(value, &file, item.line));
+            let metadata = item.metadata.iter()
+                .map(|(name, value)| (name.clone(), evaluator.expand_or_warn(value, &file, item.line)))
+                .collect();
+            items.push(EvaluatedItem {
+                item_type: item.item_type,
+                raw_include: item.include,
+                include,
+                metadata,
+                file,
+                line: item.line,
+                column: item.column,
+                end_line: item.end_line,
+                text: item.text,
+            });
+        }
+
+        Ok(EvaluatedProject {
+            project_path: project_path.to_path_buf(),
+            sdk: evaluator.project_sdk,
+            properties: evaluator.properties,
+            property_definitions: evaluator.property_definitions,
+            items,
+            warnings: evaluator.warnings,
+        })
+    }
+
+    /// Value of a property (names are case-insensitive). Undefined properties are `None`.
+    pub fn property(&self, name: &str) -> Option<&str> {
+        self.properties.get(&name.to_lowercase()).map(String::as_str)
+    }
+
+    pub fn items<'a>(&'a self, item_type: &'a str) -> impl Iterator<Item = &'a EvaluatedItem> + 'a {
+        self.items.iter().filter(move |item| item.item_type == item_type)
+    }
+
+    /// Version set for a package through central package management (`PackageVersion` items).
+    pub fn package_version(&self, package: &str) -> Option<&str> {
+        self.items("PackageVersion")
+            .filter(|item| item.include.as_deref().is_some_and(|i| i.eq_ignore_ascii_case(package)))
+            .filter_map(|item| item.metadata("Version"))
+            .last()
+    }
+}
+
+struct Evaluator {
+    project_path: PathBuf,
+    project_dir: PathBuf,
+    project_sdk: Option<String>,
+    // Keyed by lowercase name, MSBuild property names are case-insensitive
+    properties: HashMap<String, String>,
+    property_definitions: HashMap<String, (PathBuf, MsBuildProperty)>,
+    global_properties: HashSet<String>,
+    warnings: Vec<String>,
+}
+
+impl Evaluator {
+    fn new(project_path: &Path, global_properties: &HashMap<String, String>) -> Self {
+        let project_dir = project_path.parent().unwrap_or(Path::new(".")).to_path_buf();
+        let lossy = |value: Option<&std::ffi::OsStr>| value.map(|v| v.to_string_lossy().to_string()).unwrap_or_default();
+        let reserved = [
+            ("MSBuildProjectDirectory", project_dir.to_string_lossy().to_string()),
+            ("MSBuildProjectFullPath", project_path.to_string_lossy().to_string()),
+            ("MSBuildProjectFile", lossy(project_path.file_name())),
+            ("MSBuildProjectName", lossy(project_path.file_stem())),
+            ("MSBuildProjectExtension", format!(".{}", lossy(project_path.extension()))),
+        ];
+
+        // Environment variables first, so reserved and global properties take precedence
+        let properties = env::vars()
+            .chain(reserved.into_iter().map(|(name, value)| (name.to_string(), value)))
+            .chain(global_properties.iter().map(|(name, value)| (name.clone(), value.clone())))
+            .map(|(name, value)| (name.to_lowercase(), value))
+            .collect();
+
+        Evaluator {
+            project_path: project_path.to_path_buf(),
+            project_dir,
+            project_sdk: None,
+            properties,
+            property_definitions: HashMap::new(),
+            global_properties: global_properties.keys().map(|name| name.to_lowercase()).collect(),
+            warnings: Vec::new(),
+        }
+    }
+
+    fn warn(&mut self, file: &Path, line: usize, message: String) {
+        self.warnings.push(format!("{}:{}: {}", file.display(), line, message));
+    }
+
+    // First pass: properties and imports in document order. Items are kept for the second pass.
+    fn evaluate_file(&mut self, path: &Path, visited: &mut HashSet<PathBuf>, pending_items: &mut Vec<(PathBuf, MsBuildItem)>) -> Result<(), Error> {
+        if !visited.insert(path.to_path_buf()) {
+            return Ok(());
+        }
+        // Only the project itself must be readable: a malformed Directory.Build.props or imported
+        // file is reported and skipped, as if it were empty
+        let file = match MsBuildFile::load(path) {
+            Ok(file) => file,
+            Err(e) if path != self.project_path => {
+                self.warnings.push(format!("{}: skipped, the file cannot be read: {}", path.display(), e));
+                return Ok(());
+            }
+            Err(e) => return Err(e),
+        };
+        if path == self.project_path {
+            self.project_sdk = file.sdk;
+        }
+        for element in file.elements {
+            match element {
+                MsBuildElement::Property(property) => {
+                    let name = property.name.to_lowercase();
+                    if self.global_properties.contains(&name) || !self.conditions_hold(&property.conditions, path, property.line) {
+                        continue;
+                    }
+                    match self.expand(&property.value, path) {
+                        Ok(value) => {
+                            self.property_definitions.insert(name.clone(), (path.to_path_buf(), property.clone()));
+                            self.properties.insert(name, value);
+                        }
+                        Err(message) => self.warn(path, property.line, message),
+                    }
+                }
+                MsBuildElement::Import(import) => {
+                    // SDK imports come from the .NET SDK, not from the repository
+                    if import.sdk.is_some() || !self.conditions_hold(&import.conditions, path, import.line) {
+                        continue;
+                    }
+                    let project = match self.expand(&import.project, path) {
+                        Ok(project) => project,
+                        Err(message) => {
+                            self.warn(path, import.line, message);
+                            continue;
+                        }
+                    };
+                    // Import paths are relative to the importing file
+                    let directory = path.parent().unwrap_or(Path::new("."));
+                    match directory.join(project.replace('\\', "/")).canonicalize() {
+                        Ok(import_path) if import_path.is_file() => {
+                            self.evaluate_file(&import_path, visited, pending_items)?;
+                        }
+                        _ => self.warn(path, import.line, format!("imported project '{}' was not found", import.project)),
+                    }
+                }
+                MsBuildElement::Item(item) => pending_items.push((path.to_path_buf(), item)),
+            }
+        }
+        Ok(())
+    }
+
+    // A condition that cannot be evaluated is reported and treated as true
+    fn conditions_hold(&mut self, conditions: &[String], file: &Path, line: usize) -> bool {
+        for condition in conditions {
+            match self.condition(condition, file) {
+                Ok(true) => {}
+                Ok(false) => return false,
+                Err(message) => self.warn(file, line, format!("cannot evaluate condition \"{}\": {}", condition, message)),
+            }
+        }
+        true
+    }
+
+    fn expand_or_warn(&mut self, value: &str, file: &Path, line: usize) -> String {
+        match self.expand(value, file) {
+            Ok(expanded) => expanded,
+            Err(message) => {
+                self.warn(file, line, message);
+                value.to_string()
+            }
+        }
+    }
+
+    /// Expands `$(Property)` references. Undefined properties expand to an empty string, as in
+    /// MSBuild. The only property functions supported are `[MSBuild]::GetPathOfFileAbove` and
+    /// `[MSBuild]::GetDirectoryNameOfFileAbove`.
+    fn expand(&self, value: &str, this_file: &Path) -> Result<String, String> {
+        let mut result = String::new();
+        let mut rest = value;
+        while let Some(start) = rest.find("$(") {
+            result.push_str(&rest[..start]);
+            let end = start + 1 + matching_parenthesis(&rest[start + 1..])
+                .ok_or_else(|| format!("unbalanced parentheses in '{}'", value))?;
+            result.push_str(&self.property(&rest[start + 2..end], this_file)?);
+            rest = &rest[end + 1..];
+        }
+        result.push_str(rest);
+        Ok(result)
+    }
+
+    fn property(&self, expression: &str, this_file: &Path) -> Result<String, String> {
+        let expression = expression.trim();
+        let this_dir = this_file.parent().unwrap_or(Path::new("."));
+        let lossy = |value: Option<&std::ffi::OsStr>| value.map(|v| v.to_string_lossy().to_string()).unwrap_or_default();
+        // MSBuildThisFile* depend on the file containing the expression
+        match expression.to_lowercase().as_str() {
+            "msbuildthisfiledirectory" => return Ok(format!("{}{}", this_dir.to_string_lossy(), MAIN_SEPARATOR)),
+            "msbuildthisfile" => return Ok(lossy(this_file.file_name())),
+            "msbuildthisfilename" => return Ok(lossy(this_file.file_stem())),
+            "msbuildthisfilefullpath" => return Ok(this_file.to_string_lossy().to_string()),
+            _ => {}
+        }
+        if expression.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-') {
+            return Ok(self.properties.get(&expression.to_lowercase()).cloned().unwrap_or_default());
+        }
+
+        for (function, returns_directory) in [("GetPathOfFileAbove", false), ("GetDirectoryNameOfFileAbove", true)] {
+            let prefix = format!("[MSBuild]::{}(", function);
+            let Some(arguments) = strip_prefix_ignore_case(expression, &prefix).and_then(|rest| rest.strip_suffix(')')) else { continue };
+            let arguments = split_arguments(arguments).iter()
+                .map(|argument| self.expand(argument.trim().trim_matches('\''), this_file))
+                .collect::<Result<Vec<_>, _>>()?;
+            // GetPathOfFileAbove(file[, startingDirectory]), GetDirectoryNameOfFileAbove(startingDirectory, file)
+            let (file_name, start) = match (returns_directory, arguments.as_slice()) {
+                (false, [file]) => (file, this_dir.to_path_buf()),
+                (false, [file, start]) | (true, [start, file]) => (file, PathBuf::from(start)),
+                _ => return Err(format!("unexpected arguments in '$({})'", expression)),
+            };
+            let start = start.canonicalize().unwrap_or(start);
+            return Ok(match find_file_above(&start, file_name) {
+                Some(path) if returns_directory => lossy(path.parent().map(Path::as_os_str)),
+                Some(path) => path.to_string_lossy().to_string(),
+                None => String::new(),
+            });
+        }
+
+        Err(format!("unsupported expression '$({})'", expression))
+    }
+
+    fn condition(&self, condition: &str, this_file: &Path) -> Result<bool, String> {
+        let tokens = tokenize_condition(condition, |value| self.expand(value, this_file))?;
+        let mut parser = ConditionParser { tokens: &tokens, position: 0, project_dir: &self.project_dir };
+        let value = parser.or_expression()?;
+        if parser.position != tokens.len() {
+            return Err("unexpected tokens at the end".to_string());
+        }
+        value.as_bool()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Text(String),
+    Operator(&'static str),
+}
+
+// Quoted strings and $(...) references are expanded while tokenizing
+fn tokenize_condition(condition: &str, expand: impl Fn(&str) -> Result<String, String>) -> Result<Vec<Token>, String> {
+    const OPERATORS: [&str; 10] = ["==", "!=", "<=", ">=", "<", ">", "!", "(", ")", ","];
+    let mut tokens = Vec::new();
+    let mut rest = condition.trim_start();
+    while !rest.is_empty() {
+        if let Some(quoted) = rest.strip_prefix('\'') {
+            let end = quoted.find('\'').ok_or("unterminated string")?;
+            tokens.push(Token::Text(expand(&quoted[..end])?));
+            rest = &quoted[end + 1..];
+        } else if rest.starts_with("$(") {
+            let end = 1 + matching_parenthesis(&rest[1..]).ok_or("unbalanced parentheses")?;
+            tokens.push(Token::Text(expand(&rest[..=end])?));
+            rest = &rest[end + 1..];
+        } else if let Some(operator) = OPERATORS.iter().find(|op| rest.starts_with(**op)) {
+            tokens.push(Token::Operator(operator));
+            rest = &rest[operator.len()..];
+        } else {
+            let end = rest.find(|c: char| !(c.is_alphanumeric() || "_.-:[]".contains(c))).unwrap_or(rest.len());
+            if end == 0 {
+                return Err(format!("unexpected character '{}'", rest.chars().next().unwrap_or_default()));
+            }
+            tokens.push(Token::Text(rest[..end].to_string()));
+            rest = &rest[end..];
+        }
+        rest = rest.trim_start();
+    }
+    Ok(tokens)
+}
+
+#[derive(Debug, Clone)]
+enum ConditionValue {
+    Bool(bool),
+    Text(String),
+}
+
+impl ConditionValue {
+    fn as_bool(&self) -> Result<bool, String> {
+        match self {
+            ConditionValue::Bool(value) => Ok(*value),
+            ConditionValue::Text(text) => match text.to_lowercase().as_str() {
+                "true" | "on" | "yes" => Ok(true),
+                "false" | "off" | "no" => Ok(false),
+                _ => Err(format!("'{}' is not a boolean", text)),
+            },
+        }
+    }
+
+    fn into_text(self) -> String {
+        match self {
+            ConditionValue::Bool(value) => value.to_string(),
+            ConditionValue::Text(text) => text,
+        }
+    }
+}
+
+// or := and ("or" and)* ; and := not ("and" not)* ; not := "!" not | comparison ;
+// comparison := term (operator term)? ; term := "(" or ")" | function "(" args ")" | text
+struct ConditionParser<'a> {
+    tokens: &'a [Token],
+    position: usize,
+    project_dir: &'a Path,
+}
+
+impl ConditionParser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next_is_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Text(text)) if text.eq_ignore_ascii_case(keyword))
+    }
+
+    fn next_is(&self, operator: &str) -> bool {
+        matches!(self.peek(), Some(Token::Operator(op)) if *op == operator)
+    }
+
+    fn expect(&mut self, operator: &str) -> Result<(), String> {
+        if !self.next_is(operator) {
+            return Err(format!("expected '{}'", operator));
+        }
+        self.position += 1;
+        Ok(())
+    }
+
+    fn or_expression(&mut self) -> Result<ConditionValue, String> {
+        let mut value = self.and_expression()?;
+        while self.next_is_keyword("or") {
+            self.position += 1;
+            let right = self.and_expression()?.as_bool()?;
+            value = ConditionValue::Bool(value.as_bool()? || right);
+        }
+        Ok(value)
+    }
+
+    fn and_expression(&mut self) -> Result<ConditionValue, String> {
+        let mut value = self.not_expression()?;
+        while self.next_is_keyword("and") {
+            self.position += 1;
+            let right = self.not_expression()?.as_bool()?;
+            value = ConditionValue::Bool(value.as_bool()? && right);
+        }
+        Ok(value)
+    }
+
+    fn not_expression(&mut self) -> Result<ConditionValue, String> {
+        if self.next_is("!") {
+            self.position += 1;
+            return Ok(ConditionValue::Bool(!self.not_expression()?.as_bool()?));
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<ConditionValue, String> {
+        let left = self.term()?;
+        let operator = match self.peek() {
+            Some(Token::Operator(op)) if ["==", "!=", "<", ">", "<=", ">="].contains(op) => *op,
+            _ => return Ok(left),
+        };
+        self.position += 1;
+        let (left, right) = (left.into_text(), self.term()?.into_text());
+        let result = match operator {
+            // String comparisons are case-insensitive in MSBuild
+            "==" => left.eq_ignore_ascii_case(&right),
+            "!=" => !left.eq_ignore_ascii_case(&right),
+            _ => {
+                let number = |text: &str| text.trim().parse::<f64>().map_err(|_| format!("'{}' is not a number", text));
+                let (left, right) = (number(&left)?, number(&right)?);
+                match operator {
+                    "<" => left < right,
+                    ">" => left > right,
+                    "<=" => left <= right,
+                    _ => left >= right,
+                }
+            }
+        };
+        Ok(ConditionValue::Bool(result))
+    }
+
+    fn term(&mut self) -> Result<ConditionValue, String> {
+        match self.peek().cloned() {
+            Some(Token::Operator("(")) => {
+                self.position += 1;
+                let value = self.or_expression()?;
+                self.expect(")")?;
+                Ok(value)
+            }
+            Some(Token::Text(name)) if self.tokens.get(self.position + 1) == Some(&Token::Operator("(")) => {
+                self.position += 2;
+                let mut arguments = Vec::new();
+                while !self.next_is(")") {
+                    arguments.push(self.term()?.into_text());
+                    if !self.next_is(",") {
+                        break;
+                    }
+                    self.position += 1;
+                }
+                self.expect(")")?;
+                self.function(&name, &arguments)
+            }
+            Some(Token::Text(text)) => {
+                self.position += 1;
+                Ok(ConditionValue::Text(text))
+            }
+            _ => Err("unexpected end of condition".to_string()),
+        }
+    }
+
+    fn function(&self, name: &str, arguments: &[String]) -> Result<ConditionValue, String> {
+        match (name.to_lowercase().as_str(), arguments) {
+            // Relative paths in conditions are relative to the project, even in imported files
+            ("exists", [path]) => {
+                let path = path.trim();
+                Ok(ConditionValue::Bool(!path.is_empty() && self.project_dir.join(path.replace('\\', "/")).exists()))
+            }
+            ("hastrailingslash", [path]) => Ok(ConditionValue::Bool(path.ends_with('/') || path.ends_with('\\'))),
+            _ => Err(format!("unsupported function '{}'", name)),
+        }
+    }
+}
+
+// Byte index of the parenthesis closing the one that starts `text`
+fn matching_parenthesis(text: &str) -> Option<usize> {
+    let mut depth = 0;
+    for (index, c) in text.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(index);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+// Splits function arguments on the commas that are not quoted or nested
+fn split_arguments(text: &str) -> Vec<String> {
+    let mut arguments = Vec::new();
+    let mut current = String::new();
+    let mut depth = 0;
+    let mut quoted = false;
+    for c in text.chars() {
+        match c {
+            '\'' => quoted = !quoted,
+            '(' if !quoted => depth += 1,
+            ')' if !quoted => depth -= 1,
+            ',' if !quoted && depth == 0 => {
+                arguments.push(std::mem::take(&mut current));
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+    }
+    if !current.trim().is_empty() {
+        arguments.push(current);
+    }
+    arguments
+}
+
+fn strip_prefix_ignore_case<'a>(text: &'a str, prefix: &str) -> Option<&'a str> {
+    let head = text.get(..prefix.len())?;
+    head.eq_ignore_ascii_case(prefix).then(|| &text[prefix.len()..])
+}
+
+fn find_file_above(directory: &Path, name: &str) -> Option<PathBuf> {
+    directory.ancestors()
+        .map(|dir| dir.join(name))
+        .find(|path| path.is_file())
+}
+
+/// Writes the files under a new temporary directory and returns its canonical path.
+#[cfg(test)]
+pub(crate) fn test_fixture(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let root = env::temp_dir().join(format!("depscop-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    for (path, contents) in files {
+        let path = root.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, contents).unwrap();
+    }
+    root.canonicalize().unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn evaluator() -> Evaluator {
+        let global_properties = HashMap::from([("Configuration".to_string(), "Release".to_string())]);
+        Evaluator::new(Path::new("/repo/src/App/App.csproj"), &global_properties)
+    }
+
+    fn condition(condition: &str) -> Result<bool, String> {
+        evaluator().condition(condition, Path::new("/repo/src/App/App.csproj"))
+    }
+
+    #[test]
+    fn conditions_compare_case_insensitively() {
+        assert_eq!(condition("'$(Configuration)' == 'release'"), Ok(true));
+        assert_eq!(condition("'$(Configuration)|$(Platform)' == 'Release|'"), Ok(true));
+        assert_eq!(condition("'$(Configuration)' != 'Release'"), Ok(false));
+        assert_eq!(condition("$(Configuration) == Debug"), Ok(false));
+    }
+
+    #[test]
+    fn conditions_combine_with_and_or_not() {
+        assert_eq!(condition("'$(Configuration)' == 'Release' and '$(Missing)' == ''"), Ok(true));
+        assert_eq!(condition("'$(Configuration)' == 'Debug' OR ('a' == 'a' and !false)"), Ok(true));
+        assert_eq!(condition("'a' == 'b' or 'c' == 'd'"), Ok(false));
+        assert_eq!(condition("!('$(Configuration)' == 'Release')"), Ok(false));
+        assert_eq!(condition("2 < 10 and '1.5' >= 1"), Ok(true));
+    }
+
+    #[test]
+    fn undefined_properties_expand_to_empty() {
+        assert_eq!(condition("'$(Undefined)' == ''"), Ok(true));
+        assert_eq!(evaluator().expand("a$(Undefined)b", Path::new("/repo/App.csproj")), Ok("ab".to_string()));
+    }
+
+    #[test]
+    fn invalid_conditions_are_errors() {
+        assert!(condition("'$(Configuration)'").is_err());
+        assert!(condition("'a' == 'a' 'b'").is_err());
+        assert!(condition("'unterminated == 'a").is_err());
+        assert!(condition("Unknown('a')").is_err());
+    }
+
+    #[test]
+    fn exists_is_relative_to_the_project() {
+        let root = test_fixture("msbuild-exists", &[("src/App/App.csproj", "<Project />"), ("src/App/settings.json", "{}")]);
+        let project = root.join("src/App/App.csproj");
+        let evaluator = Evaluator::new(&project, &HashMap::new());
+        let imported = root.join("build/Common.props");
+        assert_eq!(evaluator.condition("Exists('settings.json')", &imported), Ok(true));
+        assert_eq!(evaluator.condition("exists('$(MSBuildProjectDirectory)/settings.json')", &imported), Ok(true));
+        assert_eq!(evaluator.condition("!Exists('missing.json')", &imported), Ok(true));
+        assert_eq!(evaluator.condition("Exists('')", &imported), Ok(false));
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn get_path_of_file_above_searches_parent_directories() {
+        let root = test_fixture("msbuild-above", &[("Common.props", "<Project />"), ("src/App/App.csproj", "<Project />")]);
+        let project = root.join("src/App/App.csproj");
+        let evaluator = Evaluator::new(&project, &HashMap::new());
+        let found = evaluator.expand("$([MSBuild]::GetPathOfFileAbove('Common.props'))", &project);
+        assert_eq!(found, Ok(root.join("Common.props").to_string_lossy().to_string()));
+        let directory = evaluator.expand("$([MSBuild]::GetDirectoryNameOfFileAbove($(MSBuildThisFileDirectory), Common.props))", &project);
+        assert_eq!(directory, Ok(root.to_string_lossy().to_string()));
+        let missing = evaluator.expand("$([MSBuild]::GetPathOfFileAbove('Missing.props'))", &project);
+        assert_eq!(missing, Ok(String::new()));
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn import_cycles_are_evaluated_once() {
+        let root = test_fixture("msbuild-cycle", &[
+            ("App.csproj", r#"<Project Sdk="Microsoft.NET.Sdk">
+  <Import Project="build/A.props" />
+  <ItemGroup><PackageReference Include="Serilog" Version="$(SerilogVersion)" /></ItemGroup>
+</Project>"#),
+            ("build/A.props", r#"<Project>
+  <PropertyGroup><SerilogVersion>3.0.0</SerilogVersion></PropertyGroup>
+  <Import Project="B.props" />
+</Project>"#),
+            ("build/B.props", r#"<Project>
+  <Import Project="A.props" />
+  <Import Project="../App.csproj" />
+  <PropertyGroup Condition="'$(SerilogVersion)' == '3.0.0'"><SerilogVersion>3.1.0</SerilogVersion></PropertyGroup>
+</Project>"#),
+        ]);
+        let project = EvaluatedProject::load(&root.join("App.csproj"), &HashMap::new()).unwrap();
+        let versions: Vec<_> = project.items("PackageReference").filter_map(|item| item.metadata("Version")).collect();
+        assert_eq!(versions, ["3.1.0"]);
+        assert!(project.warnings.is_empty(), "{:?}", project.warnings);
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn global_properties_cannot_be_overridden() {
+        let root = test_fixture("msbuild-global", &[("App.csproj", r#"<Project>
+  <PropertyGroup><Configuration>Debug</Configuration><Other>$(Configuration)</Other></PropertyGroup>
+  <ItemGroup><Compile Include="$(Configuration)/$(Other).cs" /></ItemGroup>
+</Project>"#)]);
+        let global_properties = HashMap::from([("configuration".to_string(), "Release".to_string())]);
+        let project = EvaluatedProject::load(&root.join("App.csproj"), &global_properties).unwrap();
+        let includes: Vec<_> = project.items.iter().filter_map(|item| item.include.as_deref()).collect();
+        assert_eq!(includes, ["Release/Release.cs"]);
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    fn compile_includes(project: &EvaluatedProject) -> Vec<&str> {
+        project.items("Compile").filter_map(|item| item.include.as_deref()).collect()
+    }
+
+    #[test]
+    fn nearest_directory_build_props_is_used() {
+        let root = test_fixture("msbuild-nested-props", &[
+            ("Directory.Build.props", r#"<Project><PropertyGroup><Outer>outer</Outer></PropertyGroup></Project>"#),
+            ("src/Directory.Build.props", r#"<Project>
+  <PropertyGroup><Inner>inner</Inner></PropertyGroup>
+  <ItemGroup><Compile Include="$(Outer)-$(Inner).cs" /></ItemGroup>
+</Project>"#),
+            ("src/Directory.Build.targets", r#"<Project><ItemGroup><Compile Include="$(Late).cs" /></ItemGroup></Project>"#),
+            ("src/App/App.csproj", r#"<Project><PropertyGroup><Late>late</Late></PropertyGroup></Project>"#),
+            ("Other/Other.csproj", r#"<Project><ItemGroup><Compile Include="$(Outer).cs" /></ItemGroup></Project>"#),
+        ]);
+        // Only the closest props file is imported, as with the .NET SDK
+        let app = EvaluatedProject::load(&root.join("src/App/App.csproj"), &HashMap::new()).unwrap();
+        assert_eq!(compile_includes(&app), ["-inner.cs", "late.cs"]);
+        let other = EvaluatedProject::load(&root.join("Other/Other.csproj"), &HashMap::new()).unwrap();
+        assert_eq!(compile_includes(&other), ["outer.cs"]);
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn imports_resolve_relative_to_the_importing_file() {
+        let root = test_fixture("msbuild-imports", &[
+            ("Directory.Build.props", r#"<Project>
+  <Import Project="$(MSBuildThisFileDirectory)build/Common.props" />
+</Project>"#),
+            ("build/Common.props", r#"<Project>
+  <Import Project="Versions.props" />
+  <ItemGroup><Compile Include="$(MSBuildThisFileName)-$(Version).cs" /></ItemGroup>
+</Project>"#),
+            ("build/Versions.props", r#"<Project><PropertyGroup><Version>1.2.3</Version></PropertyGroup></Project>"#),
+            ("src/App/App.csproj", "<Project />"),
+        ]);
+        let project = EvaluatedProject::load(&root.join("src/App/App.csproj"), &HashMap::new()).unwrap();
+        assert_eq!(compile_includes(&project), ["Common-1.2.3.cs"]);
+        let item = project.items("Compile").next().unwrap();
+        assert_eq!(item.file, root.join("build/Common.props"));
+        assert!(project.warnings.is_empty(), "{:?}", project.warnings);
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn missing_and_unreadable_imports_are_warnings() {
+        let root = test_fixture("msbuild-missing-imports", &[
+            ("Directory.Build.props", "<Project><PropertyGroup>"),
+            ("App.csproj", r#"<Project>
+  <Import Project="Missing.props" />
+  <Import Project="App.csproj" />
+  <ItemGroup><Compile Include="Program.cs" /></ItemGroup>
+</Project>"#),
+        ]);
+        let project = EvaluatedProject::load(&root.join("App.csproj"), &HashMap::new()).unwrap();
+        assert_eq!(compile_includes(&project), ["Program.cs"]);
+        // The project importing itself is a cycle: it is evaluated once, without a warning
+        assert_eq!(project.warnings.len(), 2, "{:?}", project.warnings);
+        assert!(project.warnings[0].contains("Directory.Build.props: skipped, the file cannot be read"), "{}", project.warnings[0]);
+        assert!(project.warnings[1].ends_with("App.csproj:2: imported project 'Missing.props' was not found"), "{}", project.warnings[1]);
+        assert!(EvaluatedProject::load(&root.join("Missing.csproj"), &HashMap::new()).is_err());
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn multi_line_items_keep_their_start_and_text() {
+        let root = test_fixture("msbuild-locations", &[("App.csproj", "<Project>
+  <!-- Référence -->
+  <ItemGroup>
+    <ProjectReference Include=\"../Entities/Entities.csproj\"
+                      Condition=\"'$(A)' != 'b>c'\">
+      <Private>false</Private>
+    </ProjectReference><ProjectReference Include=\"../IO/IO.csproj\" />
+  </ItemGroup>
+</Project>")]);
+        let project = EvaluatedProject::load(&root.join("App.csproj"), &HashMap::new()).unwrap();
+        let locations: Vec<_> = project.items("ProjectReference").map(|item| item_location(item, "ProjectReference")).collect();
+        assert_eq!(locations.len(), 2);
+        assert_eq!((locations[0].line, locations[0].column, locations[0].end_line), (4, 5, 7));
+        assert_eq!(locations[0].text, r#"<ProjectReference Include="../Entities/Entities.csproj" Condition="'$(A)' != 'b>c'"> <Private>false</Private> </ProjectReference>"#);
+        assert_eq!((locations[1].line, locations[1].column, locations[1].end_line), (7, 24, 7));
+        assert_eq!(locations[1].text, r#"<ProjectReference Include="../IO/IO.csproj" />"#);
+        assert_eq!(locations[0].file, root.join("App.csproj").to_string_lossy());
+        fs::remove_dir_all(root).unwrap();
+    }
+}
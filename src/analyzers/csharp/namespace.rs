@@ -1,81 +1,120 @@
-use std::collections::HashMap;
-use std::fs::File;
-use std::io::{Error, Read};
-use std::path::Path;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::io::Error;
+use std::path::{Component, Path, PathBuf};
+use glob::{MatchOptions, Pattern};
+use path_slash::PathExt;
 use walkdir::WalkDir;
-use regex::Regex;
 
 use crate::config::types::Config;
 use crate::config::patterns::{determine_layer, exclude_files_and_folders, exclude_namespaces};
-use crate::core::dependencies::{EdgeInfo, NodeDependencies};
+use crate::core::dependencies::{add_edge, EdgeInfo, NodeDependencies, SourceLocation};
+use crate::core::metrics::TypeCounts;
 use crate::core::node::Node;
-use crate::utils::strings::RemoveBom;
+use crate::analyzers::csharp::msbuild::{item_location, EvaluatedProject};
+use crate::analyzers::csharp::syntax::{CSharpFile, UsingDirective};
+
+pub const NAMESPACE_NODE_TYPE: &str = "namespace";
+
+const IMPLICIT_USING_KIND: &str = "implicit using";
+const TYPE_REFERENCE_KIND: &str = "type reference";
+
+// Namespaces imported by <ImplicitUsings>enable</ImplicitUsings>, per SDK
+const SDK_IMPLICIT_USINGS: &[&str] = &[
+    "System",
+    "System.Collections.Generic",
+    "System.IO",
+    "System.Linq",
+    "System.Net.Http",
+    "System.Threading",
+    "System.Threading.Tasks",
+];
+const WEB_SDK_IMPLICIT_USINGS: &[&str] = &[
+    "System.Net.Http.Json",
+    "Microsoft.AspNetCore.Builder",
+    "Microsoft.AspNetCore.Hosting",
+    "Microsoft.AspNetCore.Http",
+    "Microsoft.AspNetCore.Routing",
+    "Microsoft.Extensions.Configuration",
+    "Microsoft.Extensions.DependencyInjection",
+    "Microsoft.Extensions.Hosting",
+    "Microsoft.Extensions.Logging",
+];
+const WORKER_SDK_IMPLICIT_USINGS: &[&str] = &[
+    "Microsoft.Extensions.Configuration",
+    "Microsoft.Extensions.DependencyInjection",
+    "Microsoft.Extensions.Hosting",
+    "Microsoft.Extensions.Logging",
+];
 
 pub struct NamespaceDependencyManager;
 
+// A namespace imported by a declared namespace, or one of its types referenced in code
+struct NamespaceUsing {
+    from: String,
+    to: String,
+    location: SourceLocation,
+    // Fully qualified type name, for type references
+    type_name: Option<String>,
+}
+
+type ParsedFile = (PathBuf, CSharpFile);
+
+/// Namespaces, usings, type references and declarations read from every .cs file and project
+/// file under the analyzed folder. Scanned once per run and shared by the analyses using them.
+pub struct NamespaceScan {
+    project_files: Vec<PathBuf>,
+    compile_items: Vec<CompileItems>,
+    declared: BTreeSet<String>,
+    // Every imported namespace, including the ones imported by files without namespace declarations
+    imported: BTreeSet<String>,
+    usings: Vec<NamespaceUsing>,
+    // Project owning each declared namespace: the one declaring it in the most files
+    owners: HashMap<String, PathBuf>,
+    projects: HashMap<PathBuf, ProjectNamespaces>,
+    // Classes, structs, interfaces and records by (namespace, name): abstract, owning project.
+    // Partial declarations count once.
+    type_declarations: HashMap<(String, String), (bool, Option<PathBuf>)>,
+    // Problems met while evaluating the project files, without duplicates
+    warnings: Vec<String>,
+}
+
+/// Namespaces declared by the files of a project, and the namespaces its code uses through
+/// `using` directives (global and implicit ones included) or type references.
+#[derive(Debug, Clone, Default)]
+pub struct ProjectNamespaces {
+    pub declared: BTreeSet<String>,
+    pub used: BTreeSet<String>,
+}
+
+// `Compile` items of a project: files linked from other folders and files removed from the project
+struct CompileItems {
+    project: PathBuf,
+    includes: Vec<Pattern>,
+    removes: Vec<Pattern>,
+}
+
 impl NamespaceDependencyManager {
-    pub fn collect_nodes(root_path: &Path, config: &Config) -> Result<Vec<Node>, Error> {
+    /// Reads the C# code under `root_path`. Type references are only resolved when the
+    /// `type_references` option is set.
+    pub fn scan(root_path: &Path, config: &Config) -> Result<NamespaceScan, Error> {
+        scan_namespaces(root_path, config, config.csharp.as_ref().unwrap().type_references)
+    }
+
+    /// Warnings of the project file evaluations, e.g. imports that cannot be found
+    pub fn warnings(scan: &NamespaceScan) -> &[String] {
+        &scan.warnings
+    }
+
+    pub fn collect_nodes(scan: &NamespaceScan, config: &Config) -> Result<Vec<Node>, Error> {
         let mut namespaces: HashMap<String, Node> = HashMap::new();
-        let mut namespace_files = Vec::new();
         let csharp_config = config.csharp.as_ref().unwrap();
 
-        for entry in WalkDir::new(root_path) {
-            let entry = entry?;
-            let path = entry.path();
-            if exclude_files_and_folders(&path.to_path_buf(), &csharp_config.exclude, &csharp_config.pattern, csharp_config.case_sensitive) {
+        for namespace in scan.declared.iter().chain(&scan.imported) {
+            if exclude_namespaces(namespace, &csharp_config.exclude, &csharp_config.pattern, csharp_config.case_sensitive) {
                 continue;
             }
-            if path.extension().map_or(false, |e| e == "cs") {
-                namespace_files.push(path.to_path_buf());
-            }
-        }
-
-        let namespace_regex = Regex::new(r"^namespace\s+([\p{L}\p{N}_\.]+);?$").unwrap();
-        let using_regex = Regex::new(r"^using\s+([\p{L}\p{N}_\.]+);?$").unwrap();
-
-        for file in namespace_files {
-            let mut file = File::open(&file)?;
-            let mut contents = String::new();
-            file.read_to_string(&mut contents)?;
-
-            contents = RemoveBom::remove_bom(&contents);
-
-            for line in contents.lines() {
-                if let Some(captures) = namespace_regex.captures(line) {
-                    if let Some(current_namespace) = captures.get(1).map(|m| m.as_str().to_string()) {
-                        if exclude_namespaces(&current_namespace, &csharp_config.exclude, &csharp_config.pattern, csharp_config.case_sensitive) {
-                            continue;
-                        }
-                        if !namespaces.contains_key(&current_namespace) {
-                            let layer = determine_layer(&current_namespace, &csharp_config.namespaces, csharp_config.case_sensitive, &csharp_config.pattern);
-                            let color = config.get_color(&layer).cloned().unwrap_or_else(|| "gray".to_string());
-                            namespaces.insert(current_namespace.clone(), Node {
-                                id: current_namespace.clone(),
-                                name: current_namespace.clone(),
-                                node_type: "namespace".to_string(),
-                                layer,
-                                color,
-                            });
-                        }
-                    }
-                } else if let Some(captures) = using_regex.captures(line) {
-                    if let Some(namespace) = captures.get(1).map(|m| m.as_str().to_string()) {
-                        if exclude_namespaces(&namespace, &csharp_config.exclude, &csharp_config.pattern, csharp_config.case_sensitive) {
-                            continue;
-                        }
-                        if !namespaces.contains_key(&namespace) {
-                            let layer = determine_layer(&namespace, &csharp_config.namespaces, csharp_config.case_sensitive, &csharp_config.pattern);
-                            let color = config.get_color(&layer).cloned().unwrap_or_else(|| "gray".to_string());
-                            namespaces.insert(namespace.clone(), Node {
-                                id: namespace.clone(),
-                                name: namespace.clone(),
-                                node_type: "namespace".to_string(),
-                                layer,
-                                color,
-                            });
-                        }
-                    }
-                }
+            if !namespaces.contains_key(namespace) {
+                namespaces.insert(namespace.clone(), namespace_node(namespace, config));
             }
         }
 
@@ -83,9 +122,10 @@ impl NamespaceDependencyManager {
         namespaces.insert(global_namespace.to_string(), Node {
             id: global_namespace.to_string(),
             name: global_namespace.to_string(),
-            node_type: "namespace".to_string(),
+            node_type: NAMESPACE_NODE_TYPE.to_string(),
             layer: "unknown".to_string(),
             color: "gray".to_string(),
+            group: None,
         });
 
         let nodes: Vec<Node> = namespaces.into_values().collect();
@@ -93,81 +133,576 @@ impl NamespaceDependencyManager {
         Ok(nodes)
     }
 
-    pub fn find_dependencies(root_path: &Path, nodes: &[Node], config: &Config) -> Result<NodeDependencies, Error> {
+    pub fn find_dependencies(scan: &NamespaceScan, nodes: &[Node], config: &Config) -> Result<NodeDependencies, Error> {
         let mut node_dependencies: NodeDependencies = vec![Vec::new(); nodes.len()];
-        let mut namespace_files = Vec::new();
-        let csharp_config = config.csharp.as_ref().unwrap();
 
-        for entry in WalkDir::new(root_path) {
-            let entry = entry?;
-            let path = entry.path();
-            if exclude_files_and_folders(&path.to_path_buf(), &csharp_config.exclude, &csharp_config.pattern, csharp_config.case_sensitive) {
-                continue;
+        // Excluded namespaces have no node, so they are skipped by the lookups below
+        let node_index_map: HashMap<String, usize> = nodes.iter().enumerate()
+            .map(|(index, project)| (project.id.clone(), index))
+            .collect();
+
+        for using in &scan.usings {
+            let (Some(&parent_index), Some(&index)) = (node_index_map.get(&using.from), node_index_map.get(&using.to)) else { continue };
+            let parent_layer = &nodes[parent_index].layer;
+            let to_layer = &nodes[index].layer;
+            let allowed_layers = config.global.rules.get(parent_layer).cloned().unwrap_or_default();
+            let ok = allowed_layers.contains(to_layer);
+            let label = format!("{} -> {}", nodes[parent_index].name, nodes[index].name);
+            let types = using.type_name.iter().cloned().collect();
+            let edge = EdgeInfo { to: index, allowed: ok, label, weight: 1, locations: vec![using.location.clone()], types, merged: Vec::new() };
+            add_edge(&mut node_dependencies[parent_index], edge);
+        }
+
+        Ok(node_dependencies)
+    }
+
+    /// Id of the project owning each namespace declared under `root_path`: the project whose
+    /// files declare it most often. Files belong to the project in their folder or to the project
+    /// including them through a `Compile` item.
+    pub fn namespace_owners(scan: &NamespaceScan) -> HashMap<String, String> {
+        scan.owners.iter()
+            .map(|(namespace, project)| (namespace.clone(), project.to_string_lossy().to_string()))
+            .collect()
+    }
+
+    /// Types declared in each namespace and in each project, keyed by node id (the namespace
+    /// name or the project path).
+    pub fn type_counts(scan: &NamespaceScan) -> HashMap<String, TypeCounts> {
+        let mut counts: HashMap<String, TypeCounts> = HashMap::new();
+        for ((namespace, _), (is_abstract, project)) in &scan.type_declarations {
+            let ids = std::iter::once(namespace.clone()).chain(project.as_ref().map(|project| project.to_string_lossy().to_string()));
+            for id in ids {
+                let entry = counts.entry(id).or_default();
+                entry.types += 1;
+                entry.abstract_types += usize::from(*is_abstract);
             }
-            if path.extension().map_or(false, |e| e == "cs") {
-                namespace_files.push(path.to_path_buf());
+        }
+        counts
+    }
+
+    /// Declared and used namespaces of every scanned project, keyed by project id. With
+    /// `type_references`, code that names a type without a `using` counts too.
+    pub fn project_namespaces(scan: &NamespaceScan) -> HashMap<String, ProjectNamespaces> {
+        scan.projects.iter()
+            .map(|(project, namespaces)| (project.to_string_lossy().to_string(), namespaces.clone()))
+            .collect()
+    }
+
+    /// Truncates the namespace nodes to their first `segments` dot-separated segments and merges
+    /// the nodes and edges sharing a truncated name. Layers and edge rules are determined again on
+    /// the truncated names, and dependencies inside a merged node are dropped. Other nodes, such
+    /// as projects in `csharp:all`, are kept as they are.
+    pub fn collapse_namespaces(nodes: &[Node], node_dependencies: &NodeDependencies, segments: usize, config: &Config) -> (Vec<Node>, NodeDependencies) {
+        let mut collapsed: Vec<Node> = Vec::new();
+        let mut collapsed_index: HashMap<String, usize> = HashMap::new();
+        let mut collapsed_of = Vec::with_capacity(nodes.len());
+        for node in nodes {
+            let id = match node.node_type.as_str() {
+                NAMESPACE_NODE_TYPE => truncate_namespace(&node.id, segments),
+                _ => node.id.clone(),
+            };
+            let index = match collapsed_index.get(&id) {
+                Some(&index) => {
+                    // Namespaces owned by different projects are not drawn inside either of them
+                    if collapsed[index].group != node.group {
+                        collapsed[index].group = None;
+                    }
+                    index
+                }
+                None => {
+                    let mut collapsed_node = if id == node.id { node.clone() } else { namespace_node(&id, config) };
+                    collapsed_node.group = node.group.clone();
+                    collapsed_index.insert(id, collapsed.len());
+                    collapsed.push(collapsed_node);
+                    collapsed.len() - 1
+                }
+            };
+            collapsed_of.push(index);
+        }
+
+        // Edges of nodes standing for several namespaces list the dependencies behind them
+        let mut merges_nodes = vec![false; collapsed.len()];
+        for (node, &index) in nodes.iter().zip(&collapsed_of) {
+            merges_nodes[index] |= node.id != collapsed[index].id;
+        }
+
+        let mut collapsed_dependencies: NodeDependencies = vec![Vec::new(); collapsed.len()];
+        for (from, edges) in node_dependencies.iter().enumerate() {
+            let collapsed_from = collapsed_of[from];
+            for edge in edges {
+                let collapsed_to = collapsed_of[edge.to];
+                if collapsed_from == collapsed_to {
+                    continue;
+                }
+                let (from_node, to_node) = (&collapsed[collapsed_from], &collapsed[collapsed_to]);
+                let merged = merges_nodes[collapsed_from] || merges_nodes[collapsed_to];
+                let allowed_layers = config.global.rules.get(&from_node.layer).cloned().unwrap_or_default();
+                add_edge(&mut collapsed_dependencies[collapsed_from], EdgeInfo {
+                    to: collapsed_to,
+                    allowed: allowed_layers.contains(&to_node.layer),
+                    label: format!("{} -> {}", from_node.name, to_node.name),
+                    weight: edge.weight,
+                    locations: edge.locations.clone(),
+                    types: edge.types.clone(),
+                    merged: if merged { vec![edge.label.clone()] } else { Vec::new() },
+                });
             }
         }
 
-        let node_index_map: HashMap<String, usize> = nodes.iter().enumerate()
-            .map(|(index, project)| (project.id.clone(), index))
+        (collapsed, collapsed_dependencies)
+    }
+
+    /// Nodes of every truncation of the given namespace nodes with at least `segments` segments,
+    /// each with its own layer. Used to expand collapsed namespaces one segment at a time.
+    pub fn namespace_prefixes(nodes: &[Node], segments: usize, config: &Config) -> Vec<Node> {
+        let prefixes: BTreeSet<String> = nodes.iter()
+            .filter(|node| node.node_type == NAMESPACE_NODE_TYPE)
+            .flat_map(|node| {
+                let parts: Vec<&str> = node.id.split('.').collect();
+                (segments.max(1)..parts.len()).map(move |length| parts[..length].join("."))
+            })
             .collect();
+        prefixes.iter().map(|prefix| namespace_node(prefix, config)).collect()
+    }
+
+    /// Ids of the nodes owning each of the given absolute paths: the project the file belongs to
+    /// and, for C# files, the namespaces the file declares. Deleted files only map to their project.
+    pub fn file_owners(scan: &NamespaceScan, files: &[PathBuf]) -> Result<Vec<Vec<String>>, Error> {
+        let mut owners = Vec::new();
+        for file in files {
+            let file = normalize_path(file);
+            let mut ids: Vec<String> = owning_project(&file, &scan.project_files, &scan.compile_items)
+                .map(|project| project.to_string_lossy().to_string())
+                .into_iter()
+                .collect();
+            if file.extension().is_some_and(|e| e == "cs") && file.is_file() {
+                let names: BTreeSet<String> = CSharpFile::load(&file)?.namespaces.into_iter()
+                    .map(|declaration| declaration.name)
+                    .collect();
+                ids.extend(names);
+            }
+            owners.push(ids);
+        }
+
+        Ok(owners)
+    }
+}
 
-        let namespace_regex = Regex::new(r"^namespace\s+([\p{L}\p{N}_\.]+);?$").unwrap();
-        let using_regex = Regex::new(r"^using\s+([\p{L}\p{N}_\.]+);?$").unwrap();
+/// First `segments` dot-separated segments of a namespace name.
+pub fn truncate_namespace(namespace: &str, segments: usize) -> String {
+    namespace.split('.').take(segments.max(1)).collect::<Vec<_>>().join(".")
+}
 
-        for file in namespace_files {
-            let mut file = File::open(&file)?;
-            let mut contents = String::new();
-            file.read_to_string(&mut contents)?;
+// Node of a namespace, with the layer matching its name
+fn namespace_node(namespace: &str, config: &Config) -> Node {
+    let csharp_config = config.csharp.as_ref().unwrap();
+    let layer = determine_layer(namespace, &csharp_config.namespaces, csharp_config.case_sensitive, &csharp_config.pattern);
+    let color = config.get_color(&layer).cloned().unwrap_or_else(|| "gray".to_string());
+    Node {
+        id: namespace.to_string(),
+        name: namespace.to_string(),
+        node_type: NAMESPACE_NODE_TYPE.to_string(),
+        layer,
+        color,
+        group: None,
+    }
+}
 
-            contents = RemoveBom::remove_bom(&contents);
+// Reads the namespace declarations and using directives of every .cs file. Directives apply to
+// the namespaces in their scope, except global and implicit usings, which apply to every
+// namespace of the project owning the file (the .csproj in the nearest parent folder).
+// Names used in code are only resolved to type references when `with_references` is set.
+fn scan_namespaces(root_path: &Path, config: &Config, with_references: bool) -> Result<NamespaceScan, Error> {
+    let csharp_config = config.csharp.as_ref().unwrap();
+    let mut source_files = Vec::new();
+    let mut project_files = Vec::new();
 
-            let mut edges_info = Vec::new();
-            let mut from_node_index: Option<usize> = None;
+    for entry in WalkDir::new(root_path) {
+        let entry = entry?;
+        let path = entry.path();
+        if exclude_files_and_folders(path, &csharp_config.exclude, &csharp_config.pattern, csharp_config.case_sensitive) {
+            continue;
+        }
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("cs") => source_files.push(path.to_path_buf()),
+            Some("csproj") => project_files.push(path.to_path_buf()),
+            _ => {}
+        }
+    }
 
-            for line in contents.lines() {
-                if let Some(captures) = namespace_regex.captures(line) {
-                    let parent_namespace = captures.get(1).map(|m| m.as_str().to_string());
-                    if let Some(parent_namespace) = parent_namespace {
-                        if exclude_namespaces(&parent_namespace, &csharp_config.exclude, &csharp_config.pattern, csharp_config.case_sensitive) {
-                            continue;
-                        }
-                        from_node_index = node_index_map.get(&parent_namespace).cloned();
-                    }
-                } else if let Some(captures) = using_regex.captures(line) {
-                    let child_namespace = captures.get(1).map(|m| m.as_str().to_string());
-
-                    if let Some(child_namespace) = child_namespace {
-                        if exclude_namespaces(&child_namespace, &csharp_config.exclude, &csharp_config.pattern, csharp_config.case_sensitive) {
-                            continue;
-                        }
-                        if let Some(&index) = node_index_map.get(&child_namespace) {
-                            let to_layer = &nodes[index].layer;
-                            let allowed_layers = config.global.rules.get(to_layer).cloned().unwrap_or_default();
-                            let ok = allowed_layers.contains(to_layer);
-                            let label = format!("to -> {}", nodes[index].name);
-                            edges_info.push(EdgeInfo { to: index, allowed: ok, label });
-                        }
-                    }
+    let load = if with_references { CSharpFile::load_with_references } else { CSharpFile::load };
+    let parsed_files = source_files.into_iter()
+        .map(|path| load(&path).map(|file| (path, file)))
+        .collect::<Result<Vec<_>, Error>>()?;
+    let declared: BTreeSet<String> = parsed_files.iter()
+        .flat_map(|(_, file)| file.namespaces.iter().map(|declaration| declaration.name.clone()))
+        .collect();
+
+    // Symbol table: type names declared in each namespace
+    let mut types: HashMap<String, HashSet<String>> = HashMap::new();
+    for (_, file) in &parsed_files {
+        for declaration in &file.types {
+            if let Some(namespace) = declaration.namespace {
+                types.entry(file.namespaces[namespace].name.clone()).or_default().insert(declaration.name.clone());
+            }
+        }
+    }
+
+    // Each project file is evaluated once. A project file that cannot be read has no compile
+    // items nor implicit usings here, the project analyses report it.
+    let mut warnings: Vec<String> = Vec::new();
+    let mut evaluated: HashMap<PathBuf, EvaluatedProject> = HashMap::new();
+    for project in &project_files {
+        let Ok(project_evaluation) = EvaluatedProject::load(project, &csharp_config.properties) else { continue };
+        for warning in &project_evaluation.warnings {
+            if !warnings.contains(warning) {
+                warnings.push(warning.clone());
+            }
+        }
+        evaluated.insert(project.clone(), project_evaluation);
+    }
+
+    // Files grouped by owning project, None for files outside of any project
+    let compile_items: Vec<CompileItems> = project_files.iter()
+        .filter_map(|project| evaluated.get(project).map(|project_evaluation| compile_items(project, project_evaluation)))
+        .collect();
+    let mut projects: Vec<(Option<PathBuf>, Vec<ParsedFile>)> = Vec::new();
+    for (path, file) in parsed_files {
+        let owner = owning_project(&path, &project_files, &compile_items);
+        match projects.iter_mut().find(|(project, _)| *project == owner) {
+            Some((_, files)) => files.push((path, file)),
+            None => projects.push((owner, vec![(path, file)])),
+        }
+    }
+
+    // Number of files declaring each namespace, per project
+    let mut declaring_files: BTreeMap<(String, PathBuf), usize> = BTreeMap::new();
+    for (project, files) in &projects {
+        let Some(project) = project else { continue };
+        for (_, file) in files {
+            let names: BTreeSet<&String> = file.namespaces.iter().map(|declaration| &declaration.name).collect();
+            for name in names {
+                *declaring_files.entry((name.clone(), project.clone())).or_default() += 1;
+            }
+        }
+    }
+    let mut owners: HashMap<String, (PathBuf, usize)> = HashMap::new();
+    for ((namespace, project), count) in declaring_files {
+        if owners.get(&namespace).is_none_or(|(_, best)| count > *best) {
+            owners.insert(namespace, (project, count));
+        }
+    }
+    let owners = owners.into_iter().map(|(namespace, (project, _))| (namespace, project)).collect();
+
+    let mut scan = NamespaceScan { project_files, compile_items, declared, imported: BTreeSet::new(), usings: Vec::new(), owners, projects: HashMap::new(), type_declarations: HashMap::new(), warnings };
+    for (project, files) in projects {
+        // Usings that apply to the whole project
+        let mut project_usings: Vec<(String, SourceLocation)> = project.as_ref()
+            .and_then(|project| evaluated.get(project).map(|project_evaluation| implicit_usings(project, project_evaluation)))
+            .unwrap_or_default();
+        for (path, file) in &files {
+            for using in file.usings.iter().filter(|using| using.global) {
+                project_usings.extend(using_namespace(using, &scan.declared).map(|namespace| (namespace, using_location(path, file, using))));
+            }
+        }
+        scan.imported.extend(project_usings.iter().map(|(namespace, _)| namespace.clone()));
+        let mut namespaces = ProjectNamespaces::default();
+        namespaces.used.extend(project_usings.iter().map(|(namespace, _)| namespace.clone()));
+
+        for (path, file) in &files {
+            namespaces.declared.extend(file.namespaces.iter().map(|declaration| declaration.name.clone()));
+            for declaration in file.types.iter().filter(|declaration| matches!(declaration.kind.as_str(), "class" | "struct" | "interface" | "record")) {
+                let Some(namespace) = declaration.namespace else { continue };
+                let key = (file.namespaces[namespace].name.clone(), declaration.name.clone());
+                let entry = scan.type_declarations.entry(key).or_insert((false, project.clone()));
+                entry.0 |= declaration.is_abstract;
+            }
+            // Namespaces imported in each namespace declaration of the file
+            let mut imports: Vec<Vec<String>> = vec![project_usings.iter().map(|(namespace, _)| namespace.clone()).collect(); file.namespaces.len()];
+            for using in file.usings.iter().filter(|using| !using.global) {
+                let Some(namespace) = using_namespace(using, &scan.declared) else { continue };
+                scan.imported.insert(namespace.clone());
+                namespaces.used.insert(namespace.clone());
+                for declaration in file.namespaces_in_scope(using.scope) {
+                    imports[declaration].push(namespace.clone());
+                    scan.usings.push(NamespaceUsing {
+                        from: file.namespaces[declaration].name.clone(),
+                        to: namespace.clone(),
+                        location: using_location(path, file, using),
+                        type_name: None,
+                    });
+                }
+            }
+            for declaration in &file.namespaces {
+                for (namespace, location) in &project_usings {
+                    scan.usings.push(NamespaceUsing { from: declaration.name.clone(), to: namespace.clone(), location: location.clone(), type_name: None });
                 }
             }
 
-            if let Some(parent_index) = from_node_index {
-                for edge in &mut edges_info {
-                    let parent_layer = &nodes[parent_index].layer;
-                    let to_layer = &nodes[edge.to].layer;
-                    let allowed_layers = config.global.rules.get(parent_layer).cloned().unwrap_or_default();
-                    edge.allowed = allowed_layers.contains(to_layer);
+            for reference in &file.references {
+                let Some(scope) = reference.scope else { continue };
+                let from = &file.namespaces[scope].name;
+                let Some((namespace, type_name)) = resolve_type(&reference.name, from, &imports[scope], &types) else { continue };
+                namespaces.used.insert(namespace.clone());
+                if namespace == *from {
+                    continue;
                 }
-                let current_edges = &node_dependencies[parent_index];
-                let new_edges: Vec<EdgeInfo> = edges_info.into_iter()
-                    .filter(|edge| !current_edges.iter().any(|e| e.to == edge.to))
-                    .collect();
-                node_dependencies[parent_index].extend(new_edges);
+                scan.usings.push(NamespaceUsing {
+                    from: from.clone(),
+                    to: namespace.clone(),
+                    location: SourceLocation {
+                        file: path.to_string_lossy().to_string(),
+                        line: reference.line,
+                        column: reference.column,
+                        end_line: reference.line,
+                        kind: TYPE_REFERENCE_KIND.to_string(),
+                        text: file.line_text(reference.line),
+                    },
+                    type_name: Some(format!("{}.{}", namespace, type_name)),
+                });
             }
         }
+        if let Some(project) = project {
+            scan.projects.insert(project, namespaces);
+        }
+    }
 
-        Ok(node_dependencies)
+    Ok(scan)
+}
+
+// Project a source file belongs to: a project including it through a `Compile` item, otherwise
+// the project in the nearest parent folder, unless that project removes the file
+fn owning_project(path: &Path, project_files: &[PathBuf], compile_items: &[CompileItems]) -> Option<PathBuf> {
+    // As in MSBuild, `*` does not cross folders, only `**` does
+    let options = MatchOptions { require_literal_separator: true, ..MatchOptions::new() };
+    let matches = |patterns: &[Pattern]| patterns.iter().any(|pattern| pattern.matches_path_with(path, options));
+    if let Some(items) = compile_items.iter().find(|items| matches(&items.includes) && !matches(&items.removes)) {
+        return Some(items.project.clone());
+    }
+    let owner = project_files.iter()
+        .filter(|project| project.parent().is_some_and(|dir| path.starts_with(dir)))
+        .max_by_key(|project| project.components().count())?;
+    let removed = compile_items.iter().any(|items| items.project == *owner && matches(&items.removes));
+    (!removed).then(|| owner.clone())
+}
+
+// Reads the `Compile` items of a project. Paths are relative to the project folder and may use
+// wildcards.
+fn compile_items(project_path: &Path, evaluated: &EvaluatedProject) -> CompileItems {
+    let project_dir = project_path.parent().unwrap_or(Path::new("."));
+    let patterns = |value: &str| value.split(';')
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .filter_map(|value| Pattern::new(&normalize_path(&project_dir.join(value.replace('\\', "/"))).to_slash_lossy()).ok())
+        .collect::<Vec<_>>();
+    let mut items = CompileItems { project: project_path.to_path_buf(), includes: Vec::new(), removes: Vec::new() };
+    for item in evaluated.items("Compile") {
+        items.includes.extend(item.include.as_deref().map(patterns).unwrap_or_default());
+        items.removes.extend(item.metadata("Remove").map(patterns).unwrap_or_default());
+    }
+    items
+}
+
+// Resolves `.` and `..` components without touching the file system
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+// Namespace imported by a directive. `using static` and aliases may name a type: the namespace
+// is then the longest declared namespace containing it, or the parent name when the type comes
+// from outside the analyzed code. Non-generic aliases to unknown names are taken as namespaces.
+fn using_namespace(using: &UsingDirective, declared: &BTreeSet<String>) -> Option<String> {
+    let target = &using.target;
+    let may_be_type = using.is_static || using.generic || using.alias.is_some();
+    if !may_be_type || using.alias.is_some() && !using.generic && declared.contains(target) {
+        return Some(target.clone());
+    }
+    let containing = target.char_indices()
+        .filter(|(_, c)| *c == '.')
+        .map(|(index, _)| &target[..index])
+        .rfind(|prefix| declared.contains(*prefix));
+    match containing {
+        Some(namespace) => Some(namespace.to_string()),
+        None if using.is_static || using.generic => target.rsplit_once('.').map(|(parent, _)| parent.to_string()),
+        None => Some(target.clone()),
+    }
+}
+
+// Resolves a name used in code to a declared type, returning its namespace and name. Qualified
+// names may be relative to the enclosing namespaces (`Entities.Order` inside `Shop`). A simple
+// name, or the first part of a member access such as `Order.Create`, is looked up in the
+// enclosing namespaces and then in the imported ones, as the C# compiler does. As in
+// attributes, `Table` also matches a `TableAttribute` type.
+fn resolve_type(name: &str, namespace: &str, imports: &[String], types: &HashMap<String, HashSet<String>>) -> Option<(String, String)> {
+    let declares = |namespace: &str, type_name: &str| types.get(namespace).is_some_and(|names| names.contains(type_name));
+    let segments: Vec<&str> = name.split('.').collect();
+    let mut enclosing = vec![namespace.to_string()];
+    enclosing.extend(namespace.char_indices().rev().filter(|(_, c)| *c == '.').map(|(index, _)| namespace[..index].to_string()));
+
+    for outer in enclosing.iter().map(String::as_str).chain([""]) {
+        for length in (1..segments.len()).rev() {
+            let relative = segments[..length].join(".");
+            let candidate = if outer.is_empty() { relative } else { format!("{}.{}", outer, relative) };
+            let type_names = [segments[length].to_string(), format!("{}Attribute", segments[length])];
+            if let Some(type_name) = type_names.into_iter().find(|type_name| declares(&candidate, type_name)) {
+                return Some((candidate, type_name));
+            }
+        }
+    }
+
+    let simple_names = [segments[0].to_string(), format!("{}Attribute", segments[0])];
+    for type_name in &simple_names {
+        if let Some(found) = enclosing.iter().chain(imports).find(|namespace| declares(namespace, type_name)) {
+            return Some((found.clone(), type_name.clone()));
+        }
+    }
+    None
+}
+
+fn using_location(path: &Path, file: &CSharpFile, using: &UsingDirective) -> SourceLocation {
+    SourceLocation {
+        file: path.to_string_lossy().to_string(),
+        line: using.line,
+        column: using.column,
+        end_line: using.line,
+        kind: using.kind(),
+        text: file.line_text(using.line),
+    }
+}
+
+// Usings added by the SDK when <ImplicitUsings> is enabled, plus <Using Include="..."/> items
+fn implicit_usings(project_path: &Path, evaluated: &EvaluatedProject) -> Vec<(String, SourceLocation)> {
+    let mut usings: Vec<(String, SourceLocation)> = Vec::new();
+
+    let enabled = evaluated.property("ImplicitUsings").is_some_and(|value| value.eq_ignore_ascii_case("enable") || value.eq_ignore_ascii_case("true"));
+    if enabled {
+        let location = match evaluated.property_definitions.get("implicitusings") {
+            Some((file, property)) => SourceLocation {
+                file: file.to_string_lossy().to_string(),
+                line: property.line,
+                column: property.column,
+                end_line: property.end_line,
+                kind: IMPLICIT_USING_KIND.to_string(),
+                text: property.text.clone(),
+            },
+            // Enabled by a global property
+            None => SourceLocation { file: project_path.to_string_lossy().to_string(), line: 1, column: 1, end_line: 1, kind: IMPLICIT_USING_KIND.to_string(), text: String::new() },
+        };
+        let sdk = evaluated.sdk.as_deref().unwrap_or_default();
+        let sdk_usings = match sdk {
+            "Microsoft.NET.Sdk.Web" => [SDK_IMPLICIT_USINGS, WEB_SDK_IMPLICIT_USINGS].concat(),
+            "Microsoft.NET.Sdk.Worker" => [SDK_IMPLICIT_USINGS, WORKER_SDK_IMPLICIT_USINGS].concat(),
+            _ => SDK_IMPLICIT_USINGS.to_vec(),
+        };
+        usings.extend(sdk_usings.iter().map(|namespace| (namespace.to_string(), location.clone())));
+    }
+
+    for item in evaluated.items("Using") {
+        let location = item_location(item, IMPLICIT_USING_KIND);
+        match (&item.include, item.metadata("Remove")) {
+            (Some(include), _) => {
+                // <Using Include="X.Y.Type" Static="true"/> imports the members of a type
+                let namespace = match item.metadata("Static") {
+                    Some(value) if value.eq_ignore_ascii_case("true") => include.rsplit_once('.').map(|(parent, _)| parent.to_string()),
+                    _ => Some(include.clone()),
+                };
+                usings.extend(namespace.map(|namespace| (namespace, location)));
+            }
+            (None, Some(remove)) => usings.retain(|(namespace, _)| namespace != remove),
+            (None, None) => {}
+        }
+    }
+
+    usings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyzers::csharp::project::PROJECT_NODE_TYPE;
+    use crate::core::dependencies::test_graph;
+
+    fn location(line: usize) -> SourceLocation {
+        SourceLocation { file: "Order.cs".to_string(), line, column: 1, end_line: line, kind: "using".to_string(), text: String::new() }
+    }
+
+    // A project, then namespaces of projects P and Q
+    fn graph(config: &Config) -> (Vec<Node>, NodeDependencies) {
+        let mut project = namespace_node("/repo/Shop.Web.csproj", config);
+        project.node_type = PROJECT_NODE_TYPE.to_string();
+        let mut nodes = vec![project];
+        for (namespace, group) in [("Shop.Orders.Api", "P"), ("Shop.Orders.Domain", "P"), ("Shop.Entities.Orders", "Q"), ("Shop.Entities.Customers", "Q"), ("Shop.Entities.Shared", "P")] {
+            let mut node = namespace_node(namespace, config);
+            node.group = Some(group.to_string());
+            nodes.push(node);
+        }
+        let mut node_dependencies = test_graph(nodes.len(), &[(1, 2), (1, 3), (2, 3), (2, 4), (3, 1)]);
+        for (from, edges) in node_dependencies.iter_mut().enumerate() {
+            for edge in edges {
+                edge.label = format!("{} -> {}", nodes[from].name, nodes[edge.to].name);
+            }
+        }
+        node_dependencies[1][1].weight = 2;
+        node_dependencies[1][1].locations = vec![location(1), location(2)];
+        node_dependencies[2][0].locations = vec![location(2)];
+        node_dependencies[2][1].locations = vec![location(3)];
+        (nodes, node_dependencies)
+    }
+
+    #[test]
+    fn truncation_keeps_at_least_one_segment() {
+        assert_eq!(truncate_namespace("Shop.Orders.Api", 2), "Shop.Orders");
+        assert_eq!(truncate_namespace("Shop.Orders.Api", 5), "Shop.Orders.Api");
+        assert_eq!(truncate_namespace("Shop.Orders.Api", 0), "Shop");
+    }
+
+    #[test]
+    fn collapsed_namespaces_merge_nodes_and_edges() {
+        let config = Config::default();
+        let (nodes, node_dependencies) = graph(&config);
+        let (collapsed, collapsed_dependencies) = NamespaceDependencyManager::collapse_namespaces(&nodes, &node_dependencies, 2, &config);
+
+        let summary: Vec<_> = collapsed.iter().map(|node| (node.id.as_str(), node.layer.as_str(), node.group.as_deref())).collect();
+        // Shop.Entities merges namespaces of P and Q: it belongs to neither
+        assert_eq!(summary, [("/repo/Shop.Web.csproj", "unknown", None), ("Shop.Orders", "unknown", Some("P")), ("Shop.Entities", "core", None)]);
+
+        // Shop.Orders.Api -> Shop.Orders.Domain is inside Shop.Orders: dropped
+        assert!(collapsed_dependencies[0].is_empty());
+        assert_eq!(collapsed_dependencies[1].len(), 1);
+        let edge = &collapsed_dependencies[1][0];
+        assert_eq!(edge.to, 2);
+        assert_eq!(edge.label, "Shop.Orders -> Shop.Entities");
+        // Location 2 is shared by two edges and only counted once
+        assert_eq!(edge.weight, 3);
+        assert_eq!(edge.locations, [location(1), location(2), location(3)]);
+        assert_eq!(edge.merged, ["Shop.Orders.Api -> Shop.Entities.Orders", "Shop.Orders.Domain -> Shop.Entities.Orders", "Shop.Orders.Domain -> Shop.Entities.Customers"]);
+        // Rules are evaluated again on the collapsed layers
+        assert!(!edge.allowed);
+        assert_eq!(collapsed_dependencies[2].iter().map(|edge| (edge.to, edge.allowed)).collect::<Vec<_>>(), [(1, false)]);
+    }
+
+    #[test]
+    fn prefixes_cover_every_truncation_from_the_given_depth() {
+        let config = Config::default();
+        let (nodes, _) = graph(&config);
+        let ids = |segments| NamespaceDependencyManager::namespace_prefixes(&nodes, segments, &config).into_iter()
+            .map(|node| (node.id, node.layer))
+            .collect::<Vec<_>>();
+        let prefix = |id: &str, layer: &str| (id.to_string(), layer.to_string());
+
+        assert_eq!(ids(2), [prefix("Shop.Entities", "core"), prefix("Shop.Orders", "unknown")]);
+        assert_eq!(ids(1), [prefix("Shop", "unknown"), prefix("Shop.Entities", "core"), prefix("Shop.Orders", "unknown")]);
+        // Full names are nodes already
+        assert!(ids(3).is_empty());
     }
 }
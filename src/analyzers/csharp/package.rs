@@ -0,0 +1,173 @@
+use std::collections::HashMap;
+
+use crate::config::types::Config;
+use crate::config::patterns::{determine_layer, exclude_projects};
+use crate::core::dependencies::{add_edge, EdgeInfo, NodeDependencies};
+use crate::core::node::Node;
+use crate::analyzers::csharp::msbuild::{item_location, EvaluatedItem, EvaluatedProject};
+use crate::analyzers::csharp::project::ProjectDependencyManager;
+
+pub const PACKAGE_NODE_TYPE: &str = "package";
+
+/// Project dependencies plus the NuGet packages referenced through `PackageReference` items.
+/// Package nodes are appended after the project nodes and matched against the `projects`
+/// layer patterns by package id.
+///
+/// Nodes and dependencies are both built from the same evaluated projects, so this analysis
+/// does not implement `GraphDependencies`, whose two steps would evaluate every project twice.
+pub struct PackageDependencyManager;
+
+impl PackageDependencyManager {
+    /// Appends one node per referenced package to the given project nodes. `evaluated` holds the
+    /// projects evaluated by `ProjectDependencyManager::evaluate_projects`, indexed like `nodes`.
+    pub fn add_package_nodes(mut nodes: Vec<Node>, evaluated: &[EvaluatedProject], config: &Config) -> Vec<Node> {
+        let csharp_config = config.csharp.as_ref().unwrap();
+        let mut package_ids: HashMap<String, usize> = HashMap::new();
+
+        for project in evaluated {
+            for package_reference in package_references(project) {
+                let Some(package) = &package_reference.include else { continue };
+                let id = package_id(package);
+                if package_ids.contains_key(&id) {
+                    continue;
+                }
+                if exclude_projects(package, &csharp_config.exclude, &csharp_config.pattern, csharp_config.case_sensitive) {
+                    continue;
+                }
+                let layer = determine_layer(package, &csharp_config.projects, csharp_config.case_sensitive, &csharp_config.pattern);
+                let color = config.get_color(&layer).cloned().unwrap_or_else(|| "gray".to_string());
+                package_ids.insert(id.clone(), nodes.len());
+                nodes.push(Node {
+                    id,
+                    name: package.clone(),
+                    node_type: PACKAGE_NODE_TYPE.to_string(),
+                    layer,
+                    color,
+                    group: None,
+                });
+            }
+        }
+
+        nodes
+    }
+
+    /// Project and package dependencies of the nodes built by `add_package_nodes`, from the same
+    /// evaluated projects.
+    pub fn package_dependencies(nodes: &[Node], evaluated: &[EvaluatedProject], config: &Config) -> NodeDependencies {
+        let project_count = nodes.iter().take_while(|node| node.node_type != PACKAGE_NODE_TYPE).count();
+        let mut node_dependencies = ProjectDependencyManager::project_dependencies(&nodes[..project_count], evaluated, config);
+        node_dependencies.resize(nodes.len(), Vec::new());
+
+        let package_index_map: HashMap<&str, usize> = nodes.iter().enumerate()
+            .skip(project_count)
+            .map(|(index, node)| (node.id.as_str(), index))
+            .collect();
+
+        for (from, (project, evaluated)) in nodes[..project_count].iter().zip(evaluated).enumerate() {
+            static EMPTY_VEC: &Vec<String> = &Vec::new();
+            let allowed_layers = config.global.rules.get(&project.layer).unwrap_or(EMPTY_VEC);
+            for package_reference in package_references(evaluated) {
+                let Some(package) = &package_reference.include else { continue };
+                let Some(&index) = package_index_map.get(package_id(package).as_str()) else { continue };
+                let ok = allowed_layers.contains(&nodes[index].layer);
+                let version = package_reference.metadata("VersionOverride")
+                    .or_else(|| package_reference.metadata("Version"))
+                    .or_else(|| evaluated.package_version(package));
+                let label = match version {
+                    Some(version) => format!("{} -> {} {}", project.name, package, version),
+                    None => format!("{} -> {}", project.name, package),
+                };
+                let locations = vec![item_location(package_reference, &package_reference.item_type)];
+                add_edge(&mut node_dependencies[from], EdgeInfo { to: index, allowed: ok, label, weight: 1, locations, types: Vec::new(), merged: Vec::new() });
+            }
+        }
+
+        node_dependencies
+    }
+}
+
+// GlobalPackageReference items (Directory.Packages.props) apply to every project
+fn package_references(evaluated: &EvaluatedProject) -> impl Iterator<Item = &EvaluatedItem> {
+    evaluated.items("PackageReference").chain(evaluated.items("GlobalPackageReference"))
+}
+
+// NuGet package ids are case-insensitive
+fn package_id(package: &str) -> String {
+    format!("package:{}", package.to_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use crate::analyzers::csharp::msbuild::test_fixture;
+
+    fn project(root: &std::path::Path, path: &str, layer: &str) -> Node {
+        Node {
+            id: root.join(path).to_string_lossy().to_string(),
+            name: path.rsplit('/').next().unwrap_or(path).to_string(),
+            layer: layer.to_string(),
+            node_type: "project".to_string(),
+            color: String::new(),
+            group: None,
+        }
+    }
+
+    #[test]
+    fn packages_become_nodes_with_versioned_edges() {
+        let root = test_fixture("packages", &[
+            ("Directory.Packages.props", r#"<Project>
+  <ItemGroup>
+    <PackageVersion Include="Serilog" Version="3.1.0" />
+    <PackageVersion Include="Polly" Version="8.0.0" />
+    <GlobalPackageReference Include="StyleCop.Analyzers" Version="1.1.118" />
+  </ItemGroup>
+</Project>"#),
+            ("Shop.UseCases/Shop.UseCases.csproj", r#"<Project Sdk="Microsoft.NET.Sdk">
+  <ItemGroup>
+    <PackageReference Include="Newtonsoft.Json">
+      <Version>13.0.3</Version>
+    </PackageReference>
+    <PackageReference Include="serilog" />
+    <PackageReference Include="Polly" VersionOverride="8.1.0" />
+    <ProjectReference Include="..\Shop.Entities\Shop.Entities.csproj" />
+  </ItemGroup>
+</Project>"#),
+            ("Shop.Entities/Shop.Entities.csproj", r#"<Project Sdk="Microsoft.NET.Sdk">
+  <ItemGroup><PackageReference Include="Serilog" /></ItemGroup>
+</Project>"#),
+        ]);
+        let config = Config::default();
+        let projects = vec![
+            project(&root, "Shop.UseCases/Shop.UseCases.csproj", "usecase"),
+            project(&root, "Shop.Entities/Shop.Entities.csproj", "core"),
+        ];
+        let evaluated = ProjectDependencyManager::evaluate_projects(&projects, &config).unwrap();
+        let nodes = PackageDependencyManager::add_package_nodes(projects, &evaluated, &config);
+        let packages: Vec<_> = nodes.iter().skip(2).map(|node| (node.id.as_str(), node.name.as_str(), node.node_type.as_str())).collect();
+        assert_eq!(packages, [
+            ("package:newtonsoft.json", "Newtonsoft.Json", PACKAGE_NODE_TYPE),
+            ("package:serilog", "serilog", PACKAGE_NODE_TYPE),
+            ("package:polly", "Polly", PACKAGE_NODE_TYPE),
+            ("package:stylecop.analyzers", "StyleCop.Analyzers", PACKAGE_NODE_TYPE),
+        ]);
+
+        let dependencies = PackageDependencyManager::package_dependencies(&nodes, &evaluated, &config);
+        assert_eq!(dependencies.len(), nodes.len());
+        let labels: Vec<_> = dependencies[0].iter().map(|edge| (edge.to, edge.label.as_str())).collect();
+        assert_eq!(labels, [
+            (1, "Shop.UseCases.csproj -> Shop.Entities.csproj"),
+            (2, "Shop.UseCases.csproj -> Newtonsoft.Json 13.0.3"),
+            (3, "Shop.UseCases.csproj -> serilog 3.1.0"),
+            (4, "Shop.UseCases.csproj -> Polly 8.1.0"),
+            (5, "Shop.UseCases.csproj -> StyleCop.Analyzers 1.1.118"),
+        ]);
+        let labels: Vec<_> = dependencies[1].iter().map(|edge| (edge.to, edge.label.as_str())).collect();
+        assert_eq!(labels, [(3, "Shop.Entities.csproj -> Serilog 3.1.0"), (5, "Shop.Entities.csproj -> StyleCop.Analyzers 1.1.118")]);
+        // Packages match no layer pattern, so they are outside of the allowed layers
+        assert!(dependencies[0].iter().skip(1).all(|edge| !edge.allowed));
+        assert_eq!(dependencies[0][1].locations[0].kind, "PackageReference");
+        assert_eq!(dependencies[0][1].locations[0].line, 3);
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -1,153 +1,194 @@
-use std::collections::HashMap;
-use std::fs::File;
-use std::io::{BufReader, Error, Read};
+use std::collections::{HashMap, HashSet};
+use std::io::Error;
 use std::path::Path;
 use path_slash::PathExt;
-use serde::Deserialize;
 use walkdir::WalkDir;
 
 use crate::config::types::Config;
 use crate::config::patterns::{determine_layer, exclude_files_and_folders, exclude_projects};
-use crate::core::dependencies::{EdgeInfo, NodeDependencies};
+use crate::core::dependencies::{add_edge, EdgeInfo, NodeDependencies};
 use crate::core::node::Node;
 use crate::core::analysis::GraphDependencies;
-use crate::utils::strings::RemoveBom;
+use crate::analyzers::csharp::msbuild::{item_location, EvaluatedProject, MsBuildFile};
+use crate::analyzers::csharp::solution::{read_solution, SolutionProject};
 
-#[derive(Debug, Deserialize, PartialEq)]
-#[serde(rename_all = "PascalCase")]
-struct ProjectReference {
-    include: String,
-}
-
-#[derive(Debug, Deserialize, PartialEq)]
-#[serde(rename_all = "PascalCase")]
-struct ItemGroup {
-    #[serde(rename = "ProjectReference", default)]
-    project_references: Vec<ProjectReference>,
-}
-
-#[derive(Debug, Deserialize, PartialEq)]
-#[serde(rename_all = "PascalCase")]
-pub struct Project {
-    #[serde(rename = "ToolsVersion", default)]
-    tools_version: Option<String>,
-    #[serde(rename = "ItemGroup", default)]
-    item_groups: Vec<ItemGroup>,
-}
+pub const PROJECT_NODE_TYPE: &str = "project";
 
 pub struct ProjectDependencyManager;
 
-impl GraphDependencies for ProjectDependencyManager {
-    fn collect_nodes(root_path: &Path, config: &Config) -> Result<Vec<Node>, Error> {
+impl ProjectDependencyManager {
+    /// Collects the projects listed in a `.sln`/`.slnx` file instead of walking the folder.
+    /// Returns the nodes and the listed projects that do not exist on disk.
+    pub fn collect_solution_nodes(solution_path: &Path, config: &Config) -> Result<(Vec<Node>, Vec<SolutionProject>), Error> {
         let mut projects = Vec::new();
+        let mut missing = Vec::new();
 
-        for entry in WalkDir::new(root_path) {
-            let entry = entry?;
-            let path = entry.path();
-            let csharp_config = config.csharp.as_ref().unwrap();
-            if exclude_files_and_folders(&path.to_path_buf(), &csharp_config.exclude, &csharp_config.pattern, csharp_config.case_sensitive) {
-                continue;
-            }
-            if path.extension().map_or(false, |e| e == "csproj") {
-                let mut file = File::open(path)?;
-                let mut contents = String::new();
-                file.read_to_string(&mut contents)?;
-                contents = contents.remove_bom();
-
-                let _project: Project = match serde_xml_rs::from_str(&contents) {
-                    Ok(proj) => proj,
-                    Err(err) => {
-                        eprintln!(
-                            "Failed to parse .csproj file, possible incompatible file: {}, error: {}",
-                            path.display(),
-                            err
-                        );
-                        continue;
-                    }
-                };
-
-                let absolute_path = match path.to_str() {
-                    Some(p) => p.to_string(),
-                    None => continue,
-                };
-
-                let filename = match path.file_name().and_then(|f| f.to_str()) {
-                    Some(f) => f.to_string(),
-                    None => continue,
-                };
-
-                if exclude_projects(&filename, &csharp_config.exclude, &csharp_config.pattern, csharp_config.case_sensitive) {
+        for solution_project in read_solution(solution_path)? {
+            let canonical_path = match solution_project.path.canonicalize() {
+                Ok(path) if path.is_file() => path,
+                _ => {
+                    missing.push(solution_project);
                     continue;
                 }
+            };
+            if let Some(node) = project_node(&canonical_path, solution_project.folder.clone(), config)? {
+                if !projects.iter().any(|p: &Node| p.id == node.id) {
+                    projects.push(node);
+                }
+            }
+        }
 
-                let layer = determine_layer(&filename, &csharp_config.projects, csharp_config.case_sensitive, &csharp_config.pattern);
-                let color = config.get_color(&layer).cloned().unwrap_or_else(|| "gray".to_string());
+        Ok((projects, missing))
+    }
 
-                projects.push(Node {
-                    id: absolute_path,
-                    name: filename,
-                    node_type: "project".to_string(),
-                    layer,
-                    color,
-                });
+    /// Evaluates the project of every node. Shared props files are evaluated once per project,
+    /// their warnings are reported once.
+    pub fn evaluate_projects(nodes: &[Node], config: &Config) -> Result<Vec<EvaluatedProject>, Error> {
+        let csharp_config = config.csharp.as_ref().unwrap();
+        let mut reported_warnings: HashSet<String> = HashSet::new();
+        let mut projects = Vec::with_capacity(nodes.len());
+        for project in nodes {
+            let evaluated = EvaluatedProject::load(Path::new(&project.id), &csharp_config.properties)?;
+            for warning in &evaluated.warnings {
+                if reported_warnings.insert(warning.clone()) {
+                    eprintln!("Warning: {}", warning);
+                }
             }
+            projects.push(evaluated);
         }
-
         Ok(projects)
     }
 
-    fn find_dependencies(nodes: &[Node], config: &Config) -> Result<NodeDependencies, Error> {
+    /// `ProjectReference` dependencies between the given project nodes. `evaluated` is indexed
+    /// like `nodes`.
+    pub fn project_dependencies(nodes: &[Node], evaluated: &[EvaluatedProject], config: &Config) -> NodeDependencies {
         let mut node_dependencies = Vec::new();
         let path_index_map: HashMap<String, usize> = nodes.iter().enumerate()
             .map(|(index, project)| (project.id.clone(), index))
             .collect();
 
-        for project in nodes {
+        for (project, evaluated) in nodes.iter().zip(evaluated) {
             let project_path = Path::new(&project.id);
-            let file = File::open(project_path)?;
-            let file_reader = BufReader::new(file);
-            let csproj_data: Project = match serde_xml_rs::from_reader(file_reader) {
-                Ok(data) => data,
-                Err(err) => {
-                    eprintln!("Failed to parse .csproj file: {}", project.id);
-                    return Err(std::io::Error::new(std::io::ErrorKind::Other, err.to_string()));
-                }
-            };
-
             let mut edges_info = Vec::new();
             let project_dir = match project_path.parent() {
                 Some(dir) => dir,
                 None => continue,
             };
 
-            for item_group in &csproj_data.item_groups {
-                for project_reference in &item_group.project_references {
-                    let normalized_path = if cfg!(target_os = "windows") {
-                        Path::new(&project_reference.include).to_slash_lossy().into_owned()
-                    } else {
-                        project_reference.include.replace("\\", "/")
-                    };
-                    let dep_path = project_dir.join(normalized_path);
-                    if let Ok(canonical_dep_path) = dep_path.canonicalize() {
-                        let dep_path_str = match canonical_dep_path.to_str() {
-                            Some(s) => s,
-                            None => continue,
-                        };
-                        if let Some(&index) = path_index_map.get(dep_path_str) {
-                            let from_layer = &project.layer;
-                            let to_layer = &nodes[index].layer;
-                            static EMPTY_VEC: &Vec<String> = &Vec::new();
-                            let allowed_layers = config.global.rules.get(from_layer).unwrap_or(EMPTY_VEC);
-                            let ok = allowed_layers.contains(to_layer);
-                            let label = format!("{} -> {}", project.name, nodes[index].name);
-                            edges_info.push(EdgeInfo { to: index, allowed: ok, label });
-                        }
+            // Item paths are relative to the project, even when declared in an imported file
+            for project_reference in evaluated.items("ProjectReference") {
+                let Some(include) = &project_reference.include else { continue };
+                let normalized_path = if cfg!(target_os = "windows") {
+                    Path::new(include).to_slash_lossy().into_owned()
+                } else {
+                    include.replace("\\", "/")
+                };
+                let dep_path = project_dir.join(normalized_path);
+                let canonical_dep_path = match dep_path.canonicalize() {
+                    Ok(path) => path,
+                    Err(_) => {
+                        eprintln!(
+                            "Warning: {}:{}: unresolved ProjectReference '{}' (evaluated to '{}')",
+                            project_reference.file.display(),
+                            project_reference.line,
+                            project_reference.raw_include.as_deref().unwrap_or_default(),
+                            dep_path.display()
+                        );
+                        continue;
+                    }
+                };
+                let dep_path_str = match canonical_dep_path.to_str() {
+                    Some(s) => s,
+                    None => continue,
+                };
+                // Projects outside the analyzed folder or excluded by the configuration have no node
+                if let Some(&index) = path_index_map.get(dep_path_str) {
+                    // Shared references in Directory.Build.props also reach the referenced project itself
+                    if nodes[index].id == project.id {
+                        continue;
                     }
+                    let from_layer = &project.layer;
+                    let to_layer = &nodes[index].layer;
+                    static EMPTY_VEC: &Vec<String> = &Vec::new();
+                    let allowed_layers = config.global.rules.get(from_layer).unwrap_or(EMPTY_VEC);
+                    let ok = allowed_layers.contains(to_layer);
+                    let label = format!("{} -> {}", project.name, nodes[index].name);
+                    let locations = vec![item_location(project_reference, &project_reference.item_type)];
+                    add_edge(&mut edges_info, EdgeInfo { to: index, allowed: ok, label, weight: 1, locations, types: Vec::new(), merged: Vec::new() });
                 }
             }
             node_dependencies.push(edges_info);
         }
 
-        Ok(node_dependencies)
+        node_dependencies
+    }
+}
+
+impl GraphDependencies for ProjectDependencyManager {
+    fn collect_nodes(root_path: &Path, config: &Config) -> Result<Vec<Node>, Error> {
+        let mut projects = Vec::new();
+
+        for entry in WalkDir::new(root_path) {
+            let entry = entry?;
+            let path = entry.path();
+            let csharp_config = config.csharp.as_ref().unwrap();
+            if exclude_files_and_folders(path, &csharp_config.exclude, &csharp_config.pattern, csharp_config.case_sensitive) {
+                continue;
+            }
+            if path.extension().is_some_and(|e| e == "csproj") {
+                if let Some(node) = project_node(path, None, config)? {
+                    projects.push(node);
+                }
+            }
+        }
+
+        Ok(projects)
+    }
+
+    fn find_dependencies(nodes: &[Node], config: &Config) -> Result<NodeDependencies, Error> {
+        let evaluated = Self::evaluate_projects(nodes, config)?;
+        Ok(Self::project_dependencies(nodes, &evaluated, config))
     }
 }
+
+// Builds the node for a .csproj file, or None when the file is excluded or cannot be parsed
+fn project_node(path: &Path, group: Option<String>, config: &Config) -> Result<Option<Node>, Error> {
+    let csharp_config = config.csharp.as_ref().unwrap();
+    match MsBuildFile::load(path) {
+        Ok(_) => {}
+        Err(err) => {
+            eprintln!(
+                "Failed to parse .csproj file, possible incompatible file: {}, error: {}",
+                path.display(),
+                err
+            );
+            return Ok(None);
+        }
+    }
+
+    let absolute_path = match path.to_str() {
+        Some(p) => p.to_string(),
+        None => return Ok(None),
+    };
+
+    let filename = match path.file_name().and_then(|f| f.to_str()) {
+        Some(f) => f.to_string(),
+        None => return Ok(None),
+    };
+
+    if exclude_projects(&filename, &csharp_config.exclude, &csharp_config.pattern, csharp_config.case_sensitive) {
+        return Ok(None);
+    }
+
+    let layer = determine_layer(&filename, &csharp_config.projects, csharp_config.case_sensitive, &csharp_config.pattern);
+    let color = config.get_color(&layer).cloned().unwrap_or_else(|| "gray".to_string());
+
+    Ok(Some(Node {
+        id: absolute_path,
+        name: filename,
+        node_type: PROJECT_NODE_TYPE.to_string(),
+        layer,
+        color,
+        group,
+    }))
+}
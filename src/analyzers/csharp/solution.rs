@@ -0,0 +1,193 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Error, Read};
+use std::path::{Path, PathBuf};
+use regex::Regex;
+use xml::reader::{EventReader, XmlEvent};
+
+use crate::utils::strings::RemoveBom;
+
+// Project type GUID used by Visual Studio for solution folders
+const SOLUTION_FOLDER_TYPE: &str = "2150E333-8FDC-42A3-9474-1A3956D46DE8";
+
+/// A project listed in a solution file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SolutionProject {
+    /// Absolute path of the project file, as written in the solution (not canonicalized).
+    pub path: PathBuf,
+    /// Solution folder containing the project, e.g. `src/Web`.
+    pub folder: Option<String>,
+}
+
+/// Reads the C# projects listed in a `.sln` or `.slnx` file.
+pub fn read_solution(solution_path: &Path) -> Result<Vec<SolutionProject>, Error> {
+    let mut file = File::open(solution_path)?;
+    let mut contents = String::new();
+    file.read_to_string(&mut contents)?;
+    contents = contents.remove_bom();
+
+    let solution_dir = solution_path.parent().unwrap_or(Path::new("."));
+    let projects = if solution_path.extension().is_some_and(|e| e == "slnx") {
+        parse_slnx(&contents, solution_dir)?
+    } else {
+        parse_sln(&contents, solution_dir)
+    };
+
+    Ok(projects.into_iter()
+        .filter(|project| project.path.extension().is_some_and(|e| e == "csproj"))
+        .collect())
+}
+
+fn parse_sln(contents: &str, solution_dir: &Path) -> Vec<SolutionProject> {
+    let project_regex = Regex::new(r#"^Project\("\{([0-9A-Fa-f-]+)\}"\)\s*=\s*"([^"]*)",\s*"([^"]*)",\s*"\{([0-9A-Fa-f-]+)\}""#).unwrap();
+    let nested_regex = Regex::new(r"^\{([0-9A-Fa-f-]+)\}\s*=\s*\{([0-9A-Fa-f-]+)\}$").unwrap();
+
+    let mut folders: HashMap<String, String> = HashMap::new();
+    let mut projects: Vec<(String, String)> = Vec::new();
+    let mut parents: HashMap<String, String> = HashMap::new();
+    let mut in_nested_projects = false;
+
+    for line in contents.lines().map(str::trim) {
+        if let Some(captures) = project_regex.captures(line) {
+            let type_guid = captures[1].to_uppercase();
+            let guid = captures[4].to_uppercase();
+            if type_guid == SOLUTION_FOLDER_TYPE {
+                folders.insert(guid, captures[2].to_string());
+            } else {
+                projects.push((guid, captures[3].to_string()));
+            }
+        } else if line.starts_with("GlobalSection(NestedProjects)") {
+            in_nested_projects = true;
+        } else if line.starts_with("EndGlobalSection") {
+            in_nested_projects = false;
+        } else if in_nested_projects {
+            if let Some(captures) = nested_regex.captures(line) {
+                parents.insert(captures[1].to_uppercase(), captures[2].to_uppercase());
+            }
+        }
+    }
+
+    projects.into_iter().map(|(guid, relative_path)| {
+        // Walk up the NestedProjects chain to build the full folder path
+        let mut segments = Vec::new();
+        let mut current = parents.get(&guid);
+        while let Some(parent) = current {
+            match folders.get(parent) {
+                Some(name) if !segments.contains(name) => segments.push(name.clone()),
+                _ => break,
+            }
+            current = parents.get(parent);
+        }
+        segments.reverse();
+        SolutionProject {
+            path: solution_dir.join(relative_path.replace('\\', "/")),
+            folder: if segments.is_empty() { None } else { Some(segments.join("/")) },
+        }
+    }).collect()
+}
+
+// Projects are read in document order, whether `Folder` and `Project` elements are grouped or
+// interleaved. Folder names are full paths, written as "/src/Web/".
+fn parse_slnx(contents: &str, solution_dir: &Path) -> Result<Vec<SolutionProject>, Error> {
+    let mut projects = Vec::new();
+    let mut folders: Vec<Option<String>> = Vec::new();
+
+    let mut reader = EventReader::from_str(contents);
+    loop {
+        match reader.next().map_err(Error::other)? {
+            XmlEvent::StartElement { name, attributes, .. } => {
+                let attribute = |key: &str| attributes.iter()
+                    .find(|a| a.name.local_name == key)
+                    .map(|a| a.value.clone());
+                match name.local_name.as_str() {
+                    "Folder" => {
+                        let folder = attribute("Name").map(|name| name.trim_matches('/').to_string());
+                        folders.push(folder.filter(|name| !name.is_empty()));
+                    }
+                    "Project" => {
+                        if let Some(path) = attribute("Path") {
+                            projects.push(SolutionProject {
+                                path: solution_dir.join(path.replace('\\', "/")),
+                                folder: folders.last().cloned().flatten(),
+                            });
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            XmlEvent::EndElement { name } if name.local_name == "Folder" => {
+                folders.pop();
+            }
+            XmlEvent::EndDocument => break,
+            _ => {}
+        }
+    }
+
+    Ok(projects)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn projects(solution: &[SolutionProject]) -> Vec<(String, Option<&str>)> {
+        solution.iter()
+            .map(|project| (project.path.to_string_lossy().to_string(), project.folder.as_deref()))
+            .collect()
+    }
+
+    #[test]
+    fn sln_projects_get_their_nested_folder_path() {
+        let contents = r#"
+Microsoft Visual Studio Solution File, Format Version 12.00
+Project("{2150E333-8FDC-42A3-9474-1A3956D46DE8}") = "src", "src", "{11111111-1111-1111-1111-111111111111}"
+EndProject
+Project("{2150E333-8FDC-42A3-9474-1A3956D46DE8}") = "Web", "Web", "{22222222-2222-2222-2222-222222222222}"
+EndProject
+Project("{FAE04EC0-301F-11D3-BF4B-00C04F79EFBC}") = "Shop.Api", "src\Shop.Api\Shop.Api.csproj", "{33333333-3333-3333-3333-333333333333}"
+EndProject
+Project("{9A19103F-16F7-4668-BE54-9A1E7A4F7556}") = "Shop.Entities", "src\Shop.Entities\Shop.Entities.csproj", "{44444444-4444-4444-4444-444444444444}"
+EndProject
+Project("{9A19103F-16F7-4668-BE54-9A1E7A4F7556}") = "Tools", "Tools\Tools.csproj", "{55555555-5555-5555-5555-555555555555}"
+EndProject
+Global
+	GlobalSection(NestedProjects) = preSolution
+		{22222222-2222-2222-2222-222222222222} = {11111111-1111-1111-1111-111111111111}
+		{33333333-3333-3333-3333-333333333333} = {22222222-2222-2222-2222-222222222222}
+		{44444444-4444-4444-4444-444444444444} = {11111111-1111-1111-1111-111111111111}
+	EndGlobalSection
+EndGlobal
+"#;
+        let solution = parse_sln(contents, Path::new("/repo"));
+        assert_eq!(projects(&solution), vec![
+            ("/repo/src/Shop.Api/Shop.Api.csproj".to_string(), Some("src/Web")),
+            ("/repo/src/Shop.Entities/Shop.Entities.csproj".to_string(), Some("src")),
+            ("/repo/Tools/Tools.csproj".to_string(), None),
+        ]);
+    }
+
+    #[test]
+    fn slnx_reads_interleaved_folders_and_projects() {
+        let contents = r#"<Solution>
+  <Project Path="Tools/Tools.csproj" />
+  <Folder Name="/src/Web/">
+    <Project Path="src\Shop.Api\Shop.Api.csproj">
+      <BuildType Project="Release" />
+    </Project>
+  </Folder>
+  <Project Path="Root.csproj" />
+  <Folder Name="/src/" />
+  <Folder Name="/tests/">
+    <File Path="tests/README.md" />
+    <Project Path="tests/Shop.Tests/Shop.Tests.csproj" />
+  </Folder>
+</Solution>"#;
+        let solution = parse_slnx(contents, Path::new("/repo")).unwrap();
+        assert_eq!(projects(&solution), vec![
+            ("/repo/Tools/Tools.csproj".to_string(), None),
+            ("/repo/src/Shop.Api/Shop.Api.csproj".to_string(), Some("src/Web")),
+            ("/repo/Root.csproj".to_string(), None),
+            ("/repo/tests/Shop.Tests/Shop.Tests.csproj".to_string(), Some("tests")),
+        ]);
+    }
+}
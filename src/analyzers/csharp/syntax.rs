@@ -0,0 +1,497 @@
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{Error, Read};
+use std::path::Path;
+
+use crate::analyzers::csharp::tokenizer::{tokenize, Token, TokenKind};
+use crate::utils::strings::RemoveBom;
+
+/// A `namespace` declaration, block-scoped or file-scoped. Nested declarations get the full
+/// name (`Outer.Inner`).
+#[derive(Debug, Clone)]
+pub struct NamespaceDeclaration {
+    pub name: String,
+    /// Index of the enclosing declaration.
+    pub parent: Option<usize>,
+    pub line: usize,
+}
+
+/// A `using` directive: `using A.B;`, `global using A.B;`, `using static A.B.Type;` or
+/// `using Alias = A.B;`.
+#[derive(Debug, Clone)]
+pub struct UsingDirective {
+    /// Imported name, without generic arguments. A type for `using static` and possibly for aliases.
+    pub target: String,
+    pub global: bool,
+    pub is_static: bool,
+    pub alias: Option<String>,
+    /// The target has generic arguments, so it is a type and not a namespace.
+    pub generic: bool,
+    /// Index of the namespace declaration containing the directive, `None` for the top of the file.
+    pub scope: Option<usize>,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl UsingDirective {
+    /// Describes the form of the directive, e.g. `global using static`.
+    pub fn kind(&self) -> String {
+        let mut kind = if self.global { "global using".to_string() } else { "using".to_string() };
+        if self.is_static {
+            kind.push_str(" static");
+        }
+        if self.alias.is_some() {
+            kind.push_str(" alias");
+        }
+        kind
+    }
+}
+
+/// A class, struct, interface, enum, record or delegate declared directly in a namespace.
+/// Nested types are not recorded.
+#[derive(Debug, Clone)]
+pub struct TypeDeclaration {
+    pub name: String,
+    /// Declaring keyword: `class`, `struct`, `interface`, `enum`, `record` or `delegate`.
+    pub kind: String,
+    /// An interface, or a type declared with the `abstract` modifier.
+    pub is_abstract: bool,
+    /// Index of the namespace declaration containing the type.
+    pub namespace: Option<usize>,
+    pub line: usize,
+}
+
+/// A simple or dotted name (`Order`, `Entities.Order.Create`) used in code, outside of
+/// namespace declarations and using directives. Only recorded by [`CSharpFile::load_with_references`].
+///
+/// Without a full parser this is a heuristic: names of locals, parameters, fields and properties
+/// declared in an enclosing block (`var order = ...`, `Customer Customer { get; }`) are not
+/// recorded, nor are simple names that are called or assigned (`Order(...)`, `order = ...`).
+/// Other names may still turn out not to be types; the caller resolves them against the types
+/// declared in the analyzed code.
+#[derive(Debug, Clone)]
+pub struct NameReference {
+    pub name: String,
+    /// Index of the innermost namespace declaration containing the name.
+    pub scope: Option<usize>,
+    pub line: usize,
+    pub column: usize,
+}
+
+/// Namespace declarations, `using` directives and type declarations of a C# file.
+#[derive(Debug, Clone, Default)]
+pub struct CSharpFile {
+    pub namespaces: Vec<NamespaceDeclaration>,
+    pub usings: Vec<UsingDirective>,
+    pub types: Vec<TypeDeclaration>,
+    pub references: Vec<NameReference>,
+    /// Source lines, to quote declarations in reports.
+    pub lines: Vec<String>,
+}
+
+enum Scope {
+    Namespace(usize),
+    // Type, method or any other block
+    Other,
+}
+
+impl CSharpFile {
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        Ok(Self::parse(&read_source(path)?, false))
+    }
+
+    /// Like [`CSharpFile::load`], also recording every name used in code.
+    pub fn load_with_references(path: &Path) -> Result<Self, Error> {
+        Ok(Self::parse(&read_source(path)?, true))
+    }
+
+    pub fn parse(source: &str, with_references: bool) -> Self {
+        let tokens = tokenize(source);
+        let mut file = CSharpFile { lines: source.lines().map(str::to_string).collect(), ..CSharpFile::default() };
+        let mut scopes: Vec<Scope> = Vec::new();
+        // Names declared in each open block, plus the top level
+        let mut locals: Vec<HashSet<String>> = vec![HashSet::new()];
+        // Names declared between parentheses (parameters, `for` and `catch` variables) belong to
+        // the block that follows. They are dropped at the next `;` outside of parentheses, so
+        // `out var` declarations only hide a type until the end of their statement.
+        let mut pending: HashSet<String> = HashSet::new();
+        let mut parentheses = 0usize;
+        let mut file_scoped: Option<usize> = None;
+        let mut i = 0;
+
+        while i < tokens.len() {
+            let token = &tokens[i];
+            // Namespaces and using directives can only appear outside of types
+            let at_namespace_level = scopes.iter().all(|scope| matches!(scope, Scope::Namespace(_)));
+            let current = scopes.iter().rev()
+                .find_map(|scope| match scope {
+                    Scope::Namespace(index) => Some(*index),
+                    Scope::Other => None,
+                })
+                .or(file_scoped);
+
+            if at_namespace_level && token.is("namespace") {
+                if let Some((name, next)) = qualified_name(&tokens, i + 1) {
+                    let name = match current {
+                        Some(parent) => format!("{}.{}", file.namespaces[parent].name, name),
+                        None => name,
+                    };
+                    file.namespaces.push(NamespaceDeclaration { name, parent: current, line: token.line });
+                    let index = file.namespaces.len() - 1;
+                    i = next;
+                    match tokens.get(next) {
+                        Some(t) if t.is("{") => {
+                            scopes.push(Scope::Namespace(index));
+                            i += 1;
+                        }
+                        Some(t) if t.is(";") => {
+                            file_scoped = Some(index);
+                            i += 1;
+                        }
+                        _ => {}
+                    }
+                    continue;
+                }
+            }
+
+            if at_namespace_level && (token.is("using") || token.is("global") && tokens.get(i + 1).is_some_and(|t| t.is("using"))) {
+                // `using (...)` and `using var` statements in top-level code are not directives
+                if let Some((using, next)) = using_directive(&tokens, i, current) {
+                    file.usings.push(using);
+                    i = next;
+                    continue;
+                }
+            }
+
+            if at_namespace_level {
+                if let Some(name) = declared_type_name(&tokens, i) {
+                    let is_abstract = token.is("interface") || has_modifier(&tokens, i, "abstract");
+                    file.types.push(TypeDeclaration { name, kind: token.text.clone(), is_abstract, namespace: current, line: token.line });
+                }
+            }
+
+            let starts_name = token.kind == TokenKind::Identifier
+                && !(i > 0 && (tokens[i - 1].is(".") || tokens[i - 1].is("::")));
+            if with_references && starts_name {
+                if is_local_declaration(&tokens, i) {
+                    if parentheses > 0 {
+                        pending.insert(token.text.clone());
+                    } else if let Some(names) = locals.last_mut() {
+                        names.insert(token.text.clone());
+                    }
+                } else if let Some((name, next)) = qualified_name(&tokens, i) {
+                    let simple = next == i + 1;
+                    let first = name.split('.').next().unwrap_or_default();
+                    let shadowed = pending.contains(first) || locals.iter().any(|names| names.contains(first));
+                    let member = simple && is_called_or_assigned(&tokens, i);
+                    if !shadowed && !member {
+                        file.references.push(NameReference { name, scope: current, line: token.line, column: token.column });
+                    }
+                }
+            }
+
+            if token.is("(") {
+                parentheses += 1;
+            } else if token.is(")") {
+                parentheses = parentheses.saturating_sub(1);
+            } else if token.is(";") && parentheses == 0 {
+                pending.clear();
+            } else if token.is("{") {
+                scopes.push(Scope::Other);
+                locals.push(std::mem::take(&mut pending));
+            } else if token.is("}") {
+                scopes.pop();
+                if locals.len() > 1 {
+                    locals.pop();
+                }
+            }
+            i += 1;
+        }
+
+        file
+    }
+
+    /// Text of a 1-based line, whitespace collapsed.
+    pub fn line_text(&self, line: usize) -> String {
+        self.lines.get(line.wrapping_sub(1))
+            .map(|text| text.split_whitespace().collect::<Vec<_>>().join(" "))
+            .unwrap_or_default()
+    }
+
+    /// Indexes of the namespace declarations a directive in `scope` applies to: the declaration
+    /// itself and every declaration nested in it. Top-of-file directives apply to all of them.
+    pub fn namespaces_in_scope(&self, scope: Option<usize>) -> Vec<usize> {
+        (0..self.namespaces.len())
+            .filter(|&index| {
+                let mut current = Some(index);
+                while let Some(declaration) = current {
+                    if Some(declaration) == scope {
+                        return true;
+                    }
+                    current = self.namespaces[declaration].parent;
+                }
+                scope.is_none()
+            })
+            .collect()
+    }
+}
+
+fn read_source(path: &Path) -> Result<String, Error> {
+    let mut file = File::open(path)?;
+    let mut contents = String::new();
+    file.read_to_string(&mut contents)?;
+    Ok(contents.remove_bom())
+}
+
+// Name of the type declared by the keyword at `start`, if it is one: `class Name`,
+// `record struct Name`, `delegate void Name<T>(...)`...
+fn declared_type_name(tokens: &[Token], start: usize) -> Option<String> {
+    let keyword = &tokens[start];
+    let identifier = |index: usize| tokens.get(index)
+        .filter(|t| t.kind == TokenKind::Identifier)
+        .map(|t| t.text.clone());
+    match keyword.text.as_str() {
+        // `record class Name` is handled at the `record` keyword
+        "class" | "struct" if start > 0 && tokens[start - 1].is("record") => None,
+        "class" | "struct" | "interface" | "enum" => identifier(start + 1),
+        "record" => match tokens.get(start + 1) {
+            Some(t) if t.is("class") || t.is("struct") => identifier(start + 2),
+            _ => identifier(start + 1),
+        },
+        "delegate" => {
+            // The name is the identifier before the parameter list or the generic parameters
+            let open = (start + 1..tokens.len()).take_while(|&i| !tokens[i].is(";")).find(|&i| tokens[i].is("("))?;
+            let mut end = open;
+            if tokens[end - 1].is(">") {
+                end = (start + 1..end).rev().find(|&i| tokens[i].is("<"))?;
+            }
+            identifier(end - 1).filter(|_| end - 1 > start)
+        }
+        _ => None,
+    }
+}
+
+// Keywords that can precede an identifier without being its type
+const NON_TYPE_KEYWORDS: &[&str] = &[
+    "return", "throw", "await", "new", "is", "as", "in", "out", "ref", "yield", "case", "goto", "else",
+    "typeof", "nameof", "class", "struct", "interface", "enum", "record", "delegate", "namespace", "using",
+    "operator", "this", "base", "where", "select", "from", "let", "on", "equals", "by", "into", "when",
+    "not", "and", "or", "with",
+];
+
+// Whether the identifier at `index` is the name of a local, parameter, field or property: it
+// follows a type (`Order order`, `List<Order> orders`, `Order[] all`) and is followed by what
+// ends a declarator, an initializer or a property body
+fn is_local_declaration(tokens: &[Token], index: usize) -> bool {
+    let Some(previous) = index.checked_sub(1).map(|i| &tokens[i]) else { return false };
+    let after_type = match previous.kind {
+        TokenKind::Identifier => !NON_TYPE_KEYWORDS.contains(&previous.text.as_str()),
+        TokenKind::Punctuation => previous.is(">") || previous.is("]") || previous.is("?"),
+        TokenKind::Literal => false,
+    };
+    let ends_declarator = tokens.get(index + 1).is_some_and(|next| {
+        next.is("=") || next.is(";") || next.is(",") || next.is(")") || next.is("{") || next.is("in")
+    });
+    after_type && ends_declarator
+}
+
+// Whether the simple name at `index` is called (`Order(...)`, but not `new Order(...)`) or
+// assigned (`order = ...`), so it names a method, a local or a member rather than a type
+fn is_called_or_assigned(tokens: &[Token], index: usize) -> bool {
+    let created = index > 0 && tokens[index - 1].is("new");
+    match tokens.get(index + 1) {
+        Some(next) if next.is("(") => !created,
+        Some(next) if next.is("=") => !is_comparison(tokens, index + 1),
+        _ => false,
+    }
+}
+
+// Whether the `=` at `index` starts `==` or `=>`
+fn is_comparison(tokens: &[Token], index: usize) -> bool {
+    tokens[index].is("=") && tokens.get(index + 1).is_some_and(|next| next.is("=") || next.is(">"))
+}
+
+// Whether the declaration keyword at `start` is preceded by the given modifier
+fn has_modifier(tokens: &[Token], start: usize, modifier: &str) -> bool {
+    const MODIFIERS: &[&str] = &["public", "internal", "private", "protected", "file", "abstract", "sealed", "static", "partial", "unsafe", "new", "readonly", "ref"];
+    tokens[..start].iter().rev()
+        .take_while(|t| t.kind == TokenKind::Identifier && MODIFIERS.contains(&t.text.as_str()))
+        .any(|t| t.text == modifier)
+}
+
+// Parses a using directive starting at `start` (the `global` or `using` token). Returns the
+// directive and the index of the token after the semicolon.
+fn using_directive(tokens: &[Token], start: usize, scope: Option<usize>) -> Option<(UsingDirective, usize)> {
+    let first = &tokens[start];
+    let global = first.is("global");
+    let mut i = if global { start + 2 } else { start + 1 };
+    let is_static = tokens.get(i).is_some_and(|t| t.is("static"));
+    if is_static {
+        i += 1;
+    }
+    let mut alias = None;
+    if tokens.get(i + 1).is_some_and(|t| t.is("=")) {
+        alias = Some(tokens.get(i).filter(|t| t.kind == TokenKind::Identifier)?.text.clone());
+        i += 2;
+    }
+    let (target, mut next) = qualified_name(tokens, i)?;
+    let generic = tokens.get(next).is_some_and(|t| t.is("<"));
+    if generic {
+        // Skip generic arguments and anything after them, up to the end of the directive
+        while tokens.get(next).is_some_and(|t| !t.is(";")) {
+            next += 1;
+        }
+    }
+    if !tokens.get(next).is_some_and(|t| t.is(";")) {
+        return None;
+    }
+    let using = UsingDirective { target, global, is_static, alias, generic, scope, line: first.line, column: first.column };
+    Some((using, next + 1))
+}
+
+// Parses `A.B.C` (with an optional `global::` prefix) starting at `start`. Returns the name and
+// the index of the first token after it.
+fn qualified_name(tokens: &[Token], start: usize) -> Option<(String, usize)> {
+    let mut i = start;
+    if tokens.get(i).is_some_and(|t| t.is("global")) && tokens.get(i + 1).is_some_and(|t| t.is("::")) {
+        i += 2;
+    }
+    let mut parts = Vec::new();
+    loop {
+        let token = tokens.get(i).filter(|t| t.kind == TokenKind::Identifier)?;
+        parts.push(token.text.as_str());
+        i += 1;
+        if !tokens.get(i).is_some_and(|t| t.is(".")) {
+            break;
+        }
+        i += 1;
+    }
+    Some((parts.join("."), i))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reference_names(source: &str) -> Vec<String> {
+        CSharpFile::parse(source, true).references.into_iter().map(|reference| reference.name).collect()
+    }
+
+    #[test]
+    fn references_skip_locals_and_members_named_like_types() {
+        let names = reference_names(
+            "namespace Shop.Web {
+                class Page {
+                    public string Customer { get; set; }
+                    void Run() { var Order = 1; Order = 2; Invoice(); }
+                    void Use() { var length = Customer.Length; }
+                }
+            }",
+        );
+        assert!(!names.iter().any(|name| name == "Order" || name == "Invoice" || name.starts_with("Customer")), "{:?}", names);
+    }
+
+    #[test]
+    fn locals_and_parameters_only_hide_types_in_their_block() {
+        let names = reference_names(
+            "namespace Shop.Web {
+                class Page {
+                    void Run(int Order) { Order.ToString(); }
+                    void Pay() { { var Invoice = 1; } Invoice.Create(); Order.Create(); }
+                    abstract void Ship(int Customer);
+                    void Notify() { Customer.Find(); }
+                }
+            }",
+        );
+        assert!(!names.iter().any(|name| name == "Order.ToString"), "{:?}", names);
+        for expected in ["Invoice.Create", "Order.Create", "Customer.Find"] {
+            assert!(names.iter().any(|name| name == expected), "{} missing from {:?}", expected, names);
+        }
+    }
+
+    #[test]
+    fn references_keep_types_in_declarations_and_creations() {
+        let names = reference_names(
+            "namespace Shop.Web {
+                class Page {
+                    Order order = new Invoice();
+                    void Run(Customer customer) { Order.Create(); }
+                }
+            }",
+        );
+        for expected in ["Order", "Invoice", "Customer", "Order.Create"] {
+            assert!(names.iter().any(|name| name == expected), "{} missing from {:?}", expected, names);
+        }
+        assert!(!names.iter().any(|name| name == "order" || name == "customer"), "{:?}", names);
+    }
+
+    #[test]
+    fn nested_namespaces_get_full_names() {
+        let file = CSharpFile::parse(
+            "namespace Shop { namespace Web.Pages { class Page { } } class Root { } }
+            namespace Other { }",
+            false,
+        );
+        let names: Vec<_> = file.namespaces.iter().map(|namespace| namespace.name.as_str()).collect();
+        assert_eq!(names, ["Shop", "Shop.Web.Pages", "Other"]);
+        assert_eq!(file.namespaces[1].parent, Some(0));
+        assert_eq!(file.namespaces[2].parent, None);
+        let types: Vec<_> = file.types.iter().map(|t| (t.name.as_str(), t.namespace)).collect();
+        assert_eq!(types, [("Page", Some(1)), ("Root", Some(0))]);
+    }
+
+    #[test]
+    fn file_scoped_namespace_contains_the_rest_of_the_file() {
+        let file = CSharpFile::parse("using System;\nnamespace Shop.Web;\nusing Shop.Data;\npublic abstract class Page { }", false);
+        assert_eq!(file.namespaces.len(), 1);
+        assert_eq!(file.namespaces[0].name, "Shop.Web");
+        assert_eq!(file.usings[0].scope, None);
+        assert_eq!(file.usings[1].scope, Some(0));
+        assert_eq!(file.types[0].namespace, Some(0));
+        assert!(file.types[0].is_abstract);
+    }
+
+    #[test]
+    fn namespace_keywords_inside_types_and_strings_are_ignored() {
+        let file = CSharpFile::parse(
+            "namespace Shop { class Page { string s = \"namespace Fake { }\"; void Run() { using (var x = Open()) { } } } }",
+            false,
+        );
+        assert_eq!(file.namespaces.len(), 1);
+        assert!(file.usings.is_empty());
+    }
+
+    #[test]
+    fn using_directive_forms() {
+        let file = CSharpFile::parse(
+            "global using System.Linq;
+            using static System.Math;
+            global using static Shop.Data.Helpers;
+            using Json = Newtonsoft.Json;
+            using Items = System.Collections.Generic.List<Shop.Item>;
+            using global::Shop.Web;
+            namespace Shop { using Shop.Data; }",
+            false,
+        );
+        let usings: Vec<_> = file.usings.iter()
+            .map(|using| (using.kind(), using.target.as_str(), using.alias.as_deref(), using.generic, using.scope))
+            .collect();
+        assert_eq!(usings, [
+            ("global using".to_string(), "System.Linq", None, false, None),
+            ("using static".to_string(), "System.Math", None, false, None),
+            ("global using static".to_string(), "Shop.Data.Helpers", None, false, None),
+            ("using alias".to_string(), "Newtonsoft.Json", Some("Json"), false, None),
+            ("using alias".to_string(), "System.Collections.Generic.List", Some("Items"), true, None),
+            ("using".to_string(), "Shop.Web", None, false, None),
+            ("using".to_string(), "Shop.Data", None, false, Some(0)),
+        ]);
+    }
+
+    #[test]
+    fn namespaces_in_scope_follow_nesting() {
+        let file = CSharpFile::parse("namespace A { namespace B { } } namespace C { }", false);
+        assert_eq!(file.namespaces_in_scope(None), [0, 1, 2]);
+        assert_eq!(file.namespaces_in_scope(Some(0)), [0, 1]);
+        assert_eq!(file.namespaces_in_scope(Some(2)), [2]);
+    }
+}
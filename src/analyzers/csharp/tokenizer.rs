@@ -0,0 +1,345 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    /// Identifier or keyword, without the `@` prefix of verbatim identifiers.
+    Identifier,
+    /// String, character or numeric literal. The text is not kept.
+    Literal,
+    /// A single punctuation character, or `::`.
+    Punctuation,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub text: String,
+    /// 1-based line and column of the first character.
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Token {
+    pub fn is(&self, text: &str) -> bool {
+        self.kind != TokenKind::Literal && self.text == text
+    }
+}
+
+struct Lexer {
+    chars: Vec<char>,
+    position: usize,
+    line: usize,
+    column: usize,
+    // One entry per open #if: (current branch active, a branch was already taken)
+    conditionals: Vec<(bool, bool)>,
+}
+
+/// Splits C# source into the tokens the dependency analysis needs: identifiers (keywords
+/// included), literals and punctuation, with their position. Comments, the contents of string
+/// and character literals, preprocessor directives and code in inactive `#if` regions never
+/// produce tokens.
+pub fn tokenize(source: &str) -> Vec<Token> {
+    let mut lexer = Lexer { chars: source.chars().collect(), position: 0, line: 1, column: 1, conditionals: Vec::new() };
+    let mut tokens = Vec::new();
+    let mut line_start = true;
+
+    while let Some(c) = lexer.peek(0) {
+        if c == '\n' {
+            lexer.advance();
+            line_start = true;
+            continue;
+        }
+        if c.is_whitespace() {
+            lexer.advance();
+            continue;
+        }
+        if c == '#' && line_start {
+            lexer.directive();
+            continue;
+        }
+        line_start = false;
+        if !lexer.active() {
+            lexer.skip_line();
+            continue;
+        }
+        if let Some(token) = lexer.token() {
+            tokens.push(token);
+        }
+    }
+
+    tokens
+}
+
+impl Lexer {
+    fn peek(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.position + offset).copied()
+    }
+
+    fn advance(&mut self) -> Option<char> {
+        let c = self.peek(0)?;
+        self.position += 1;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    fn active(&self) -> bool {
+        self.conditionals.iter().all(|(active, _)| *active)
+    }
+
+    fn skip_line(&mut self) {
+        while self.peek(0).is_some_and(|c| c != '\n') {
+            self.advance();
+        }
+    }
+
+    // Handles #if/#elif/#else/#endif; other directives (#region, #pragma...) are ignored.
+    // Only literal `true`/`false` conditions are evaluated: unknown symbols are considered
+    // defined, so the first branch of `#if DEBUG ... #else ... #endif` is the active one.
+    fn directive(&mut self) {
+        let start = self.position;
+        self.skip_line();
+        let line: String = self.chars[start + 1..self.position].iter().collect();
+        let line = line.split("//").next().unwrap_or_default().trim();
+        let (name, condition) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let condition = !matches!(condition.trim(), "false" | "!true");
+        match name {
+            "if" => self.conditionals.push((condition, condition)),
+            "elif" => {
+                if let Some((active, taken)) = self.conditionals.last_mut() {
+                    *active = !*taken && condition;
+                    *taken |= *active;
+                }
+            }
+            "else" => {
+                if let Some((active, taken)) = self.conditionals.last_mut() {
+                    *active = !*taken;
+                    *taken = true;
+                }
+            }
+            "endif" => {
+                self.conditionals.pop();
+            }
+            _ => {}
+        }
+    }
+
+    fn token(&mut self) -> Option<Token> {
+        let (line, column) = (self.line, self.column);
+        let make = |kind, text: String| Some(Token { kind, text, line, column });
+        let c = self.peek(0)?;
+        let next = self.peek(1);
+
+        match c {
+            '/' if next == Some('/') => {
+                self.skip_line();
+                None
+            }
+            '/' if next == Some('*') => {
+                self.advance();
+                self.advance();
+                while self.peek(0).is_some() && !(self.peek(0) == Some('*') && self.peek(1) == Some('/')) {
+                    self.advance();
+                }
+                self.advance();
+                self.advance();
+                None
+            }
+            '"' | '\'' | '$' | '@' if self.string_start().is_some() => {
+                self.string();
+                make(TokenKind::Literal, String::new())
+            }
+            _ if c.is_alphabetic() || c == '_' || (c == '@' && next.is_some_and(|n| n.is_alphabetic() || n == '_')) => {
+                if c == '@' {
+                    self.advance();
+                }
+                let mut text = String::new();
+                while let Some(c) = self.peek(0).filter(|c| c.is_alphanumeric() || *c == '_') {
+                    text.push(c);
+                    self.advance();
+                }
+                make(TokenKind::Identifier, text)
+            }
+            _ if c.is_ascii_digit() => {
+                // A dot only continues the number when followed by a digit (1.5 but not 1.ToString())
+                while match self.peek(0) {
+                    Some('.') => self.peek(1).is_some_and(|n| n.is_ascii_digit()),
+                    Some(c) => c.is_alphanumeric() || c == '_',
+                    None => false,
+                } {
+                    self.advance();
+                }
+                make(TokenKind::Literal, String::new())
+            }
+            ':' if next == Some(':') => {
+                self.advance();
+                self.advance();
+                make(TokenKind::Punctuation, "::".to_string())
+            }
+            _ => {
+                self.advance();
+                make(TokenKind::Punctuation, c.to_string())
+            }
+        }
+    }
+
+    // Returns (interpolated, verbatim, quotes) when a string or char literal starts here
+    fn string_start(&self) -> Option<(bool, bool, usize)> {
+        let mut offset = 0;
+        let (mut interpolated, mut verbatim) = (false, false);
+        while let Some(c) = self.peek(offset) {
+            match c {
+                '$' => interpolated = true,
+                '@' => verbatim = true,
+                _ => break,
+            }
+            offset += 1;
+        }
+        match self.peek(offset)? {
+            '\'' if offset == 0 => Some((false, false, 0)),
+            '"' => {
+                let quotes = (offset..).take_while(|&i| self.peek(i) == Some('"')).count();
+                Some((interpolated, verbatim, quotes))
+            }
+            _ => None,
+        }
+    }
+
+    fn string(&mut self) {
+        let Some((interpolated, verbatim, quotes)) = self.string_start() else { return };
+        while self.peek(0).is_some_and(|c| c == '$' || c == '@') {
+            self.advance();
+        }
+
+        // Character literal
+        if quotes == 0 {
+            self.advance();
+            while let Some(c) = self.advance() {
+                match c {
+                    '\\' => {
+                        self.advance();
+                    }
+                    '\'' | '\n' => break,
+                    _ => {}
+                }
+            }
+            return;
+        }
+
+        // Raw string literal: """...""", closed by the same number of quotes
+        if quotes >= 3 && !verbatim {
+            for _ in 0..quotes {
+                self.advance();
+            }
+            while self.peek(0).is_some() {
+                if (0..quotes).all(|i| self.peek(i) == Some('"')) {
+                    for _ in 0..quotes {
+                        self.advance();
+                    }
+                    return;
+                }
+                self.advance();
+            }
+            return;
+        }
+
+        // Empty string: ""
+        if quotes == 2 && !verbatim {
+            self.advance();
+            self.advance();
+            return;
+        }
+
+        self.advance();
+        while let Some(c) = self.advance() {
+            match c {
+                '\\' if !verbatim => {
+                    self.advance();
+                }
+                '"' if verbatim && self.peek(0) == Some('"') => {
+                    self.advance();
+                }
+                '"' => return,
+                '\n' if !verbatim => return,
+                '{' if interpolated && self.peek(0) == Some('{') => {
+                    self.advance();
+                }
+                '{' if interpolated => self.interpolation(),
+                _ => {}
+            }
+        }
+    }
+
+    // Skips an interpolation hole, which may contain nested braces and strings
+    fn interpolation(&mut self) {
+        let mut depth = 1;
+        while let Some(c) = self.peek(0) {
+            if self.string_start().is_some() {
+                self.string();
+                continue;
+            }
+            self.advance();
+            match c {
+                '{' => depth += 1,
+                '}' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(source: &str) -> Vec<String> {
+        tokenize(source).into_iter()
+            .filter(|token| token.kind != TokenKind::Literal)
+            .map(|token| token.text)
+            .collect()
+    }
+
+    #[test]
+    fn verbatim_strings_end_at_a_single_quote() {
+        assert_eq!(texts(r#"a = @"C:\dir\ ""{ quoted }"" "; b"#), ["a", "=", ";", "b"]);
+    }
+
+    #[test]
+    fn raw_strings_keep_quotes_and_braces() {
+        let source = "a = \"\"\"\n  \"quoted\" { } \"\"\n  \"\"\"; b";
+        assert_eq!(texts(source), ["a", "=", ";", "b"]);
+    }
+
+    #[test]
+    fn interpolated_strings_skip_holes_with_nested_strings() {
+        let source = r#"a = $"{x} {{ {(y ? "}" : $"{z}")} }}"; b"#;
+        assert_eq!(texts(source), ["a", "=", ";", "b"]);
+        let verbatim = "a = $@\"{x}\n\"\"{y}\"\"\"; b";
+        assert_eq!(texts(verbatim), ["a", "=", ";", "b"]);
+    }
+
+    #[test]
+    fn comments_with_braces_produce_no_tokens() {
+        let source = "namespace A { // }\n /* { } } */ class B { }\n}";
+        assert_eq!(texts(source), ["namespace", "A", "{", "class", "B", "{", "}", "}"]);
+    }
+
+    #[test]
+    fn inactive_conditional_blocks_are_skipped() {
+        let source = "#if false\nnamespace Old {\n#else\nnamespace New {\n#endif\n#if DEBUG\nclass A { }\n#elif true\nclass B { }\n#endif\n}";
+        assert_eq!(texts(source), ["namespace", "New", "{", "class", "A", "{", "}", "}"]);
+    }
+
+    #[test]
+    fn positions_are_one_based() {
+        let tokens = tokenize("a\n  @b");
+        assert_eq!((tokens[1].text.as_str(), tokens[1].line, tokens[1].column), ("b", 2, 3));
+    }
+}
@@ -1,7 +1,7 @@
 use regex::Regex;
 use glob::Pattern;
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::Path;
 use crate::config::types::{StringOrVec, Exclude};
 
 pub fn determine_layer(name: &str, layer_configs: &HashMap<String, StringOrVec>, case_sensitive: bool, pattern_type: &str) -> String {
@@ -64,7 +64,7 @@ fn matches_pattern(item: &str, patterns: &[String], pattern_type: &str, case_sen
     false
 }
 
-pub fn exclude_files_and_folders(path: &PathBuf, exclude: &Exclude, pattern_type: &str, case_sensitive: bool) -> bool {
+pub fn exclude_files_and_folders(path: &Path, exclude: &Exclude, pattern_type: &str, case_sensitive: bool) -> bool {
     let path_str = match path.to_str() {
         Some(p) => p,
         None => return false,
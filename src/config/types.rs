@@ -25,6 +25,17 @@ pub struct Global {
     pub colors: HashMap<String, String>,
     pub rules: HashMap<String, Vec<String>>,
     pub toggles: Toggles,
+    /// Stable-dependencies principle rule, checked with `--check` when present.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stable_dependencies: Option<StableDependencies>,
+}
+
+/// Flags edges whose target is more unstable than their source.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy)]
+pub struct StableDependencies {
+    /// How much more unstable than the source the target may be before the edge is flagged.
+    #[serde(default)]
+    pub tolerance: f64,
 }
 
 #[derive(Serialize, Deserialize, Debug, Default)]
@@ -49,6 +60,12 @@ pub struct Csharp {
     pub exclude: Exclude,
     pub projects: HashMap<String, StringOrVec>,
     pub namespaces: HashMap<String, StringOrVec>,
+    /// MSBuild global properties used when evaluating project files.
+    #[serde(default)]
+    pub properties: HashMap<String, String>,
+    /// Also scan code for type references in the namespace analysis.
+    #[serde(default)]
+    pub type_references: bool,
 }
 
 #[derive(Serialize, Deserialize, Debug, Default)]
@@ -87,14 +104,15 @@ impl Default for Config {
         Self {
             global: Global {
                 layers: vec!["core".to_string(), "io".to_string(), "usecase".to_string()],
-                colors: colors,
-                rules: rules,
+                colors,
+                rules,
                 toggles: Toggles {
                     show_valid_dependencies: true,
                     show_invalid_dependencies: true,
                     show_recognized_nodes: true,
                     show_unrecognized_nodes: true,
                 },
+                stable_dependencies: None,
             },
             csharp: Some(Csharp {
                 pattern: "regex".to_string(),
@@ -121,6 +139,8 @@ impl Default for Config {
                 .iter()
                 .cloned()
                 .collect(),
+                properties: HashMap::new(),
+                type_references: false,
             }),
             javascript: Some(Javascript {
                 pattern: "wildcard".to_string(),
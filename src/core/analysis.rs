@@ -1,4 +1,5 @@
-use std::{collections::HashSet, path::Path};
+use std::collections::{HashSet, VecDeque};
+use std::path::Path;
 use std::io::Error;
 
 use crate::config::types::Config;
@@ -9,56 +10,232 @@ pub trait GraphDependencies {
     fn find_dependencies(projects: &[Node], config: &Config) -> Result<NodeDependencies, Error>;
 }
 
-pub fn detect_cycles(nodes: &[Node], node_dependencies: &NodeDependencies) -> bool {
-    let mut has_cycle = false;
-    for i in 0..nodes.len() {
-        let mut visiting = HashSet::new();
-        let mut visited = HashSet::new();
-        let mut stack = Vec::new();
-        if dfs(i, &mut stack, &mut visiting, &mut visited, node_dependencies, nodes) {
-            println!("===============================");
-            has_cycle = true;
+/// A strongly connected component of the dependency graph that contains at least one cycle.
+#[derive(Debug, Clone)]
+pub struct StronglyConnectedComponent {
+    /// Indices of every node in the component, in ascending order.
+    pub members: Vec<usize>,
+    /// Shortest cycle found inside the component, as node indices. The cycle is closed by an
+    /// edge from the last element back to the first one.
+    pub cycle: Vec<usize>,
+    /// Edges `(from, to)` that close a cycle inside the component. Removing all of them leaves
+    /// the component acyclic.
+    pub closing_edges: Vec<(usize, usize)>,
+}
+
+/// Returns every strongly connected component of the graph (Tarjan's algorithm), including
+/// single nodes without cycles. Components are emitted in reverse topological order: a
+/// component only depends on components that appear before it.
+pub fn strongly_connected_components(node_dependencies: &NodeDependencies) -> Vec<Vec<usize>> {
+    let mut tarjan = Tarjan {
+        deps: node_dependencies,
+        index: 0,
+        indices: vec![None; node_dependencies.len()],
+        low_links: vec![0; node_dependencies.len()],
+        on_stack: vec![false; node_dependencies.len()],
+        stack: Vec::new(),
+        components: Vec::new(),
+    };
+    for node in 0..node_dependencies.len() {
+        if tarjan.indices[node].is_none() {
+            tarjan.visit(node);
+        }
+    }
+    tarjan.components
+}
+
+/// Finds every cycle in the graph, grouped by strongly connected component. Each component is
+/// reported once, no matter how many cycles run through it.
+pub fn detect_cycles(node_dependencies: &NodeDependencies) -> Vec<StronglyConnectedComponent> {
+    let mut cycles = Vec::new();
+    for mut members in strongly_connected_components(node_dependencies) {
+        let is_cyclic = members.len() > 1
+            || node_dependencies[members[0]].iter().any(|edge| edge.to == members[0]);
+        if !is_cyclic {
+            continue;
         }
+        members.sort_unstable();
+        let member_set: HashSet<usize> = members.iter().cloned().collect();
+        let cycle = shortest_cycle(&members, &member_set, node_dependencies);
+        let closing_edges = closing_edges(&members, &member_set, node_dependencies);
+        cycles.push(StronglyConnectedComponent { members, cycle, closing_edges });
     }
-    if !has_cycle {
-        println!("No circular dependencies detected.");
+    cycles.sort_by_key(|component| component.members[0]);
+    cycles
+}
+
+struct Tarjan<'a> {
+    deps: &'a NodeDependencies,
+    index: usize,
+    indices: Vec<Option<usize>>,
+    low_links: Vec<usize>,
+    on_stack: Vec<bool>,
+    stack: Vec<usize>,
+    components: Vec<Vec<usize>>,
+}
+
+impl Tarjan<'_> {
+    fn visit(&mut self, node: usize) {
+        self.indices[node] = Some(self.index);
+        self.low_links[node] = self.index;
+        self.index += 1;
+        self.stack.push(node);
+        self.on_stack[node] = true;
+
+        let deps = self.deps;
+        for edge in &deps[node] {
+            match self.indices[edge.to] {
+                None => {
+                    self.visit(edge.to);
+                    self.low_links[node] = self.low_links[node].min(self.low_links[edge.to]);
+                }
+                Some(index) if self.on_stack[edge.to] => {
+                    self.low_links[node] = self.low_links[node].min(index);
+                }
+                Some(_) => {}
+            }
+        }
+
+        if Some(self.low_links[node]) == self.indices[node] {
+            let mut component = Vec::new();
+            while let Some(member) = self.stack.pop() {
+                self.on_stack[member] = false;
+                component.push(member);
+                if member == node {
+                    break;
+                }
+            }
+            self.components.push(component);
+        }
+    }
+}
+
+// Breadth-first search from every member back to itself, restricted to the component.
+fn shortest_cycle(members: &[usize], member_set: &HashSet<usize>, deps: &NodeDependencies) -> Vec<usize> {
+    let mut best: Option<Vec<usize>> = None;
+    for &start in members {
+        let mut parents: Vec<Option<usize>> = vec![None; deps.len()];
+        let mut queue = VecDeque::from([start]);
+        let mut closing_node = None;
+        'search: while let Some(current) = queue.pop_front() {
+            for edge in &deps[current] {
+                if edge.to == start {
+                    closing_node = Some(current);
+                    break 'search;
+                }
+                if member_set.contains(&edge.to) && parents[edge.to].is_none() {
+                    parents[edge.to] = Some(current);
+                    queue.push_back(edge.to);
+                }
+            }
+        }
+        if let Some(mut current) = closing_node {
+            let mut cycle = vec![current];
+            while current != start {
+                current = parents[current].unwrap();
+                cycle.push(current);
+            }
+            cycle.reverse();
+            if best.as_ref().is_none_or(|b| cycle.len() < b.len()) {
+                best = Some(cycle);
+            }
+        }
+    }
+    best.unwrap_or_default()
+}
+
+// Depth-first search restricted to the component; every back edge closes a cycle.
+fn closing_edges(members: &[usize], member_set: &HashSet<usize>, deps: &NodeDependencies) -> Vec<(usize, usize)> {
+    let mut edges = Vec::new();
+    let mut visited = HashSet::new();
+    let mut visiting = HashSet::new();
+    for &member in members {
+        if !visited.contains(&member) {
+            collect_back_edges(member, member_set, deps, &mut visiting, &mut visited, &mut edges);
+        }
     }
-    has_cycle
+    edges
 }
 
-fn dfs(
+fn collect_back_edges(
     node: usize,
-    stack: &mut Vec<usize>,
+    member_set: &HashSet<usize>,
+    deps: &NodeDependencies,
     visiting: &mut HashSet<usize>,
     visited: &mut HashSet<usize>,
-    deps: &NodeDependencies,
-    nodes: &[Node],
-) -> bool {
-    if visiting.contains(&node) {
-        let cycle_start_index = stack.iter().position(|&x| x == node).unwrap();
-        println!("Cycle detected in dependencies starting at '{}':", nodes[node].name);
-        for &index in &stack[cycle_start_index..] {
-            print!("{} -> ", nodes[index].name);
+    edges: &mut Vec<(usize, usize)>,
+) {
+    visiting.insert(node);
+    for edge in &deps[node] {
+        if !member_set.contains(&edge.to) {
+            continue;
+        }
+        if visiting.contains(&edge.to) {
+            if !edges.contains(&(node, edge.to)) {
+                edges.push((node, edge.to));
+            }
+        } else if !visited.contains(&edge.to) {
+            collect_back_edges(edge.to, member_set, deps, visiting, visited, edges);
         }
-        println!("{}", nodes[node].name);
-        return true;
     }
+    visiting.remove(&node);
+    visited.insert(node);
+}
 
-    if visited.contains(&node) {
-        return false;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::dependencies::test_graph;
+
+    fn sorted(mut components: Vec<Vec<usize>>) -> Vec<Vec<usize>> {
+        for members in &mut components {
+            members.sort_unstable();
+        }
+        components
     }
 
-    visiting.insert(node);
-    stack.push(node);
+    #[test]
+    fn components_come_in_reverse_topological_order() {
+        // 0 -> 1 <-> 2 -> 3, 4 alone
+        let graph = test_graph(5, &[(0, 1), (1, 2), (2, 1), (2, 3)]);
+        assert_eq!(sorted(strongly_connected_components(&graph)), [vec![3], vec![1, 2], vec![0], vec![4]]);
+    }
 
-    for next in &deps[node] {
-        if dfs(next.to, stack, visiting, visited, deps, nodes) {
-            return true;
-        }
+    #[test]
+    fn acyclic_graphs_have_no_cycles() {
+        let graph = test_graph(4, &[(0, 1), (0, 2), (1, 3), (2, 3)]);
+        assert!(detect_cycles(&graph).is_empty());
     }
 
-    stack.pop();
-    visiting.remove(&node);
-    visited.insert(node);
-    false
+    #[test]
+    fn each_component_is_reported_once() {
+        // Two cycles sharing node 1 (1 -> 2 -> 1 and 1 -> 3 -> 4 -> 1), and a separate one 5 <-> 6
+        let graph = test_graph(7, &[(0, 1), (1, 2), (2, 1), (1, 3), (3, 4), (4, 1), (5, 6), (6, 5)]);
+        let cycles = detect_cycles(&graph);
+        assert_eq!(cycles.len(), 2);
+        assert_eq!(cycles[0].members, [1, 2, 3, 4]);
+        assert_eq!(cycles[0].cycle, [1, 2]);
+        assert_eq!(cycles[1].members, [5, 6]);
+    }
+
+    #[test]
+    fn self_dependencies_are_cycles() {
+        let graph = test_graph(2, &[(0, 1), (1, 1)]);
+        let cycles = detect_cycles(&graph);
+        assert_eq!(cycles.len(), 1);
+        assert_eq!(cycles[0].members, [1]);
+        assert_eq!(cycles[0].cycle, [1]);
+        assert_eq!(cycles[0].closing_edges, [(1, 1)]);
+    }
+
+    #[test]
+    fn removing_closing_edges_breaks_every_cycle() {
+        let edges = [(0, 1), (1, 2), (2, 0), (2, 3), (3, 1), (3, 0)];
+        let cycles = detect_cycles(&test_graph(4, &edges));
+        assert_eq!(cycles.len(), 1);
+        let remaining: Vec<_> = edges.iter().copied()
+            .filter(|edge| !cycles[0].closing_edges.contains(edge))
+            .collect();
+        assert!(detect_cycles(&test_graph(4, &remaining)).is_empty());
+    }
 }
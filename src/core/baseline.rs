@@ -0,0 +1,141 @@
+use std::collections::BTreeSet;
+use std::fs::File;
+use std::io::{BufReader, Error};
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+
+use crate::core::check::CheckResult;
+use crate::core::node::Node;
+
+pub const BASELINE_FILE_NAME: &str = "depscop-baseline.json";
+pub const BASELINE_SCHEMA_VERSION: u32 = 1;
+
+/// A disallowed edge accepted by the baseline, identified by stable node ids and layers.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct BaselineEntry {
+    pub from: String,
+    pub from_layer: String,
+    pub to: String,
+    pub to_layer: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Baseline {
+    pub schema_version: u32,
+    pub analysis: String,
+    pub violations: BTreeSet<BaselineEntry>,
+}
+
+impl Baseline {
+    /// Captures every disallowed edge of `result` so it can be accepted in later runs.
+    pub fn from_check_result(analysis: &str, nodes: &[Node], result: &CheckResult, root_path: &Path) -> Self {
+        let violations = result.violations.values()
+            .flatten()
+            .map(|&(from, to)| entry(nodes, from, to, root_path))
+            .collect();
+        Baseline {
+            schema_version: BASELINE_SCHEMA_VERSION,
+            analysis: analysis.to_string(),
+            violations,
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let file = File::open(path)?;
+        serde_json::from_reader(BufReader::new(file)).map_err(Error::other)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        let file = File::create(path)?;
+        serde_json::to_writer_pretty(file, self).map_err(Error::other)
+    }
+
+    /// Removes the violations accepted by the baseline from `result`, and records the baseline
+    /// entries that no longer occur so the file can be ratcheted down.
+    pub fn apply(&self, nodes: &[Node], result: &mut CheckResult, root_path: &Path) {
+        let mut seen = BTreeSet::new();
+        for edges in result.violations.values_mut() {
+            edges.retain(|&(from, to)| {
+                let key = entry(nodes, from, to, root_path);
+                let known = self.violations.contains(&key);
+                if known {
+                    seen.insert(key);
+                    result.baselined_violations += 1;
+                }
+                !known
+            });
+        }
+        result.violations.retain(|_, edges| !edges.is_empty());
+        result.fixed_baseline_entries = self.violations.difference(&seen).cloned().collect();
+    }
+}
+
+fn entry(nodes: &[Node], from: usize, to: usize, root_path: &Path) -> BaselineEntry {
+    BaselineEntry {
+        from: nodes[from].stable_id(root_path),
+        from_layer: nodes[from].layer.clone(),
+        to: nodes[to].stable_id(root_path),
+        to_layer: nodes[to].layer.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(id: &str, layer: &str) -> Node {
+        Node {
+            id: id.to_string(),
+            name: id.to_string(),
+            layer: layer.to_string(),
+            node_type: "project".to_string(),
+            color: String::new(),
+            group: None,
+        }
+    }
+
+    fn check_result(violations: &[(usize, usize)], nodes: &[Node]) -> CheckResult {
+        let mut result = CheckResult::default();
+        for &(from, to) in violations {
+            result.violations.entry((nodes[from].layer.clone(), nodes[to].layer.clone()))
+                .or_default()
+                .push((from, to));
+        }
+        result
+    }
+
+    #[test]
+    fn baselined_violations_are_removed_from_the_result() {
+        let nodes = [
+            node("/repo/src/Domain/Domain.csproj", "domain"),
+            node("/repo/src/Web/Web.csproj", "web"),
+            node("/repo/src/Data/Data.csproj", "data"),
+        ];
+        let accepted = Baseline::from_check_result("csharp:projects", &nodes, &check_result(&[(0, 1), (0, 2)], &nodes), Path::new("/repo"));
+        assert_eq!(accepted.violations.iter().next().map(|entry| entry.from.as_str()), Some("src/Domain/Domain.csproj"));
+
+        // Checked out elsewhere: (0, 2) is gone and (2, 1) is new
+        let moved: Vec<Node> = nodes.iter()
+            .map(|n| node(&n.id.replace("/repo", "/checkout"), &n.layer))
+            .collect();
+        let mut result = check_result(&[(0, 1), (2, 1)], &moved);
+        accepted.apply(&moved, &mut result, Path::new("/checkout"));
+
+        assert_eq!(result.baselined_violations, 1);
+        assert_eq!(result.violations.into_iter().collect::<Vec<_>>(), [(("data".to_string(), "web".to_string()), vec![(2, 1)])]);
+        let fixed: Vec<_> = result.fixed_baseline_entries.iter().map(|entry| (entry.from.as_str(), entry.to.as_str())).collect();
+        assert_eq!(fixed, [("src/Domain/Domain.csproj", "src/Data/Data.csproj")]);
+    }
+
+    #[test]
+    fn entries_match_on_layers_too() {
+        let nodes = [node("A", "domain"), node("B", "web")];
+        let accepted = Baseline::from_check_result("csharp:namespaces", &nodes, &check_result(&[(0, 1)], &nodes), Path::new("/repo"));
+        let relayered = [node("A", "core"), node("B", "web")];
+        let mut result = check_result(&[(0, 1)], &relayered);
+        accepted.apply(&relayered, &mut result, Path::new("/repo"));
+        assert_eq!(result.violation_count(), 1);
+        assert_eq!(result.baselined_violations, 0);
+        assert_eq!(result.fixed_baseline_entries.len(), 1);
+    }
+}
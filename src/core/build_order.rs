@@ -0,0 +1,95 @@
+use crate::core::analysis::{detect_cycles, strongly_connected_components, StronglyConnectedComponent};
+use crate::core::dependencies::NodeDependencies;
+
+/// Topological build order of the graph, grouped into waves that can build in parallel.
+#[derive(Debug, Clone, Default)]
+pub struct BuildOrder {
+    /// Each wave only depends on the waves before it. A step is a single node or, when the graph
+    /// has cycles, every node of a strongly connected component, which must be built together.
+    pub waves: Vec<Vec<Vec<usize>>>,
+    /// Longest chain of steps, in build order. Its length is the number of waves.
+    pub critical_path: Vec<Vec<usize>>,
+    /// Cycles that prevent a valid order; their components appear as single steps.
+    pub cycles: Vec<StronglyConnectedComponent>,
+}
+
+/// Orders the strongly connected components of the graph by their longest path to a node
+/// without dependencies: a step goes in the wave after its deepest dependency.
+pub fn build_order(node_dependencies: &NodeDependencies) -> BuildOrder {
+    // Components come in reverse topological order, so dependencies are leveled first
+    let mut components = strongly_connected_components(node_dependencies);
+    let mut component_of = vec![0; node_dependencies.len()];
+    for (index, members) in components.iter_mut().enumerate() {
+        members.sort_unstable();
+        for &member in members.iter() {
+            component_of[member] = index;
+        }
+    }
+
+    let mut levels = vec![0; components.len()];
+    let mut previous: Vec<Option<usize>> = vec![None; components.len()];
+    for (index, members) in components.iter().enumerate() {
+        for &member in members {
+            for edge in &node_dependencies[member] {
+                let dependency = component_of[edge.to];
+                if dependency != index && levels[dependency] + 1 > levels[index] {
+                    levels[index] = levels[dependency] + 1;
+                    previous[index] = Some(dependency);
+                }
+            }
+        }
+    }
+
+    let mut waves: Vec<Vec<Vec<usize>>> = vec![Vec::new(); levels.iter().max().map_or(0, |max| max + 1)];
+    for (index, members) in components.iter().enumerate() {
+        waves[levels[index]].push(members.clone());
+    }
+    for wave in &mut waves {
+        wave.sort_by_key(|members| members[0]);
+    }
+
+    let mut critical_path = Vec::new();
+    let mut current = (0..components.len()).max_by_key(|&index| (levels[index], std::cmp::Reverse(components[index][0])));
+    while let Some(index) = current {
+        critical_path.push(components[index].clone());
+        current = previous[index];
+    }
+    critical_path.reverse();
+
+    BuildOrder { waves, critical_path, cycles: detect_cycles(node_dependencies) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::dependencies::test_graph;
+
+    #[test]
+    fn nodes_build_after_their_deepest_dependency() {
+        // 0 -> 1 -> 3, 0 -> 2 -> 3, 4 -> 3, 5 alone
+        let order = build_order(&test_graph(6, &[(0, 1), (0, 2), (1, 3), (2, 3), (4, 3)]));
+        assert_eq!(order.waves, [
+            vec![vec![3], vec![5]],
+            vec![vec![1], vec![2], vec![4]],
+            vec![vec![0]],
+        ]);
+        assert_eq!(order.critical_path, [vec![3], vec![1], vec![0]]);
+        assert!(order.cycles.is_empty());
+    }
+
+    #[test]
+    fn cycles_build_as_a_single_step() {
+        // 0 -> 1 <-> 2 -> 3
+        let order = build_order(&test_graph(4, &[(0, 1), (1, 2), (2, 1), (2, 3)]));
+        assert_eq!(order.waves, [vec![vec![3]], vec![vec![1, 2]], vec![vec![0]]]);
+        assert_eq!(order.critical_path, [vec![3], vec![1, 2], vec![0]]);
+        assert_eq!(order.cycles.len(), 1);
+    }
+
+    #[test]
+    fn critical_path_prefers_the_lowest_index_on_ties() {
+        let order = build_order(&test_graph(4, &[(1, 0), (3, 2)]));
+        assert_eq!(order.critical_path, [vec![0], vec![1]]);
+        assert!(build_order(&Vec::new()).waves.is_empty());
+    }
+}
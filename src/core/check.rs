@@ -0,0 +1,142 @@
+use std::collections::BTreeMap;
+
+use crate::core::analysis::{detect_cycles, StronglyConnectedComponent};
+use crate::config::types::StableDependencies;
+use crate::core::baseline::BaselineEntry;
+use crate::core::dependencies::NodeDependencies;
+use crate::core::metrics::{coupling, instability};
+use crate::core::node::Node;
+
+/// Exit code bits reported by the check mode. They are OR-ed together when several
+/// categories fail, so `1` stays reserved for regular errors.
+pub const EXIT_DISALLOWED_EDGES: i32 = 2;
+pub const EXIT_CYCLES: i32 = 4;
+pub const EXIT_UNKNOWN_NODES: i32 = 8;
+pub const EXIT_UNSTABLE_DEPENDENCIES: i32 = 16;
+
+/// Edge `(from, to)` given as node indices.
+pub type Violation = (usize, usize);
+
+/// Edge pointing to a node more unstable than its source, against the stable-dependencies principle.
+#[derive(Debug, Clone)]
+pub struct UnstableDependency {
+    pub from: usize,
+    pub to: usize,
+    pub from_instability: f64,
+    pub to_instability: f64,
+}
+
+#[derive(Debug, Default)]
+pub struct CheckResult {
+    /// Disallowed edges grouped by `(from_layer, to_layer)`.
+    pub violations: BTreeMap<(String, String), Vec<Violation>>,
+    pub cycles: Vec<StronglyConnectedComponent>,
+    /// Indices of nodes that did not match any layer pattern.
+    pub unknown_nodes: Vec<usize>,
+    /// Edges breaking the stable-dependencies rule, when it is configured.
+    pub unstable_dependencies: Vec<UnstableDependency>,
+    /// Number of disallowed edges accepted by the baseline and removed from `violations`.
+    pub baselined_violations: usize,
+    /// Baseline entries that no longer occur in the graph.
+    pub fixed_baseline_entries: Vec<BaselineEntry>,
+}
+
+impl CheckResult {
+    pub fn violation_count(&self) -> usize {
+        self.violations.values().map(Vec::len).sum()
+    }
+
+    pub fn exit_code(&self) -> i32 {
+        let mut code = 0;
+        if !self.violations.is_empty() {
+            code |= EXIT_DISALLOWED_EDGES;
+        }
+        if !self.cycles.is_empty() {
+            code |= EXIT_CYCLES;
+        }
+        if !self.unknown_nodes.is_empty() {
+            code |= EXIT_UNKNOWN_NODES;
+        }
+        if !self.unstable_dependencies.is_empty() {
+            code |= EXIT_UNSTABLE_DEPENDENCIES;
+        }
+        code
+    }
+
+    pub fn passed(&self) -> bool {
+        self.exit_code() == 0
+    }
+}
+
+pub fn check_dependencies(nodes: &[Node], node_dependencies: &NodeDependencies, stable_dependencies: Option<&StableDependencies>) -> CheckResult {
+    let mut violations: BTreeMap<(String, String), Vec<Violation>> = BTreeMap::new();
+    for (from, deps) in node_dependencies.iter().enumerate() {
+        for dep in deps.iter().filter(|dep| !dep.allowed) {
+            violations.entry((nodes[from].layer.clone(), nodes[dep.to].layer.clone()))
+                .or_default()
+                .push((from, dep.to));
+        }
+    }
+
+    CheckResult {
+        violations,
+        cycles: detect_cycles(node_dependencies),
+        unknown_nodes: (0..nodes.len()).filter(|&i| nodes[i].layer == "unknown").collect(),
+        unstable_dependencies: stable_dependencies
+            .map(|rule| find_unstable_dependencies(node_dependencies, rule.tolerance))
+            .unwrap_or_default(),
+        ..Default::default()
+    }
+}
+
+/// Edges whose target instability exceeds the source instability by more than `tolerance`.
+/// Instability is computed from the fan-in and fan-out of the graph.
+pub fn find_unstable_dependencies(node_dependencies: &NodeDependencies, tolerance: f64) -> Vec<UnstableDependency> {
+    let instabilities: Vec<f64> = coupling(node_dependencies).into_iter()
+        .map(|(afferent, efferent)| instability(afferent, efferent))
+        .collect();
+
+    let mut unstable = Vec::new();
+    for (from, deps) in node_dependencies.iter().enumerate() {
+        for dep in deps.iter().filter(|dep| dep.to != from) {
+            if instabilities[dep.to] > instabilities[from] + tolerance {
+                unstable.push(UnstableDependency {
+                    from,
+                    to: dep.to,
+                    from_instability: instabilities[from],
+                    to_instability: instabilities[dep.to],
+                });
+            }
+        }
+    }
+    unstable
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::dependencies::test_graph;
+
+    fn edges(unstable: &[UnstableDependency]) -> Vec<(usize, usize)> {
+        unstable.iter().map(|dependency| (dependency.from, dependency.to)).collect()
+    }
+
+    // 0, 1 and 2 depend on 3 (I = 1/4), which depends on 4 (I = 2/3), which depends on 5 and 6 (I = 0)
+    fn graph() -> NodeDependencies {
+        test_graph(7, &[(0, 3), (1, 3), (2, 3), (3, 4), (4, 5), (4, 6), (5, 5)])
+    }
+
+    #[test]
+    fn dependencies_on_less_stable_nodes_are_reported() {
+        let unstable = find_unstable_dependencies(&graph(), 0.0);
+        assert_eq!(edges(&unstable), [(3, 4)]);
+        assert_eq!(unstable[0].from_instability, 0.25);
+        assert!((unstable[0].to_instability - 2.0 / 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn differences_within_the_tolerance_are_accepted() {
+        assert_eq!(edges(&find_unstable_dependencies(&graph(), 0.4)), [(3, 4)]);
+        assert!(find_unstable_dependencies(&graph(), 0.5).is_empty());
+    }
+}
@@ -1,9 +1,142 @@
+/// Place in a source file where a dependency is declared.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceLocation {
+    pub file: String,
+    /// 1-based line and column where the declaration starts.
+    pub line: usize,
+    pub column: usize,
+    /// Line where the declaration ends, after `line` for a multi-line MSBuild element.
+    pub end_line: usize,
+    /// How the dependency is declared, e.g. `ProjectReference`, `using` or `global using`.
+    pub kind: String,
+    /// The declaration as written: the MSBuild element or the source line, whitespace collapsed.
+    pub text: String,
+}
+
 #[derive(Debug, Clone)]
 pub struct EdgeInfo {
     pub to: usize,
     pub allowed: bool,
     pub label: String,
+    /// Number of declarations contributing to the edge: `ProjectReference` items, using
+    /// directives, type references...
+    pub weight: usize,
+    pub locations: Vec<SourceLocation>,
+    /// Fully qualified names of the types referenced through this edge (type reference scan).
+    pub types: Vec<String>,
+    /// Labels of the edges aggregated into this one when nodes are grouped.
+    pub merged: Vec<String>,
+}
+
+impl EdgeInfo {
+    /// Adds the declarations of another edge between the same nodes. A location already
+    /// recorded on this edge is not counted twice.
+    pub fn merge(&mut self, other: EdgeInfo) {
+        let mut added = other.locations.is_empty();
+        for location in other.locations {
+            if !self.locations.contains(&location) {
+                self.locations.push(location);
+                added = true;
+            }
+        }
+        if added {
+            self.weight += other.weight;
+        }
+        for type_name in other.types {
+            if !self.types.contains(&type_name) {
+                self.types.push(type_name);
+            }
+        }
+        self.merged.extend(other.merged);
+    }
+}
+
+/// Adds an edge to the edges of a node, merging it into the existing edge to the same target.
+pub fn add_edge(edges: &mut EdgesInfo, edge: EdgeInfo) {
+    match edges.iter_mut().find(|existing| existing.to == edge.to) {
+        Some(existing) => existing.merge(edge),
+        None => edges.push(edge),
+    }
 }
 
 pub type EdgesInfo = Vec<EdgeInfo>;
 pub type NodeDependencies = Vec<EdgesInfo>;
+
+/// Graph of `node_count` nodes with allowed, unlabeled edges `(from, to)`.
+#[cfg(test)]
+pub(crate) fn test_graph(node_count: usize, edges: &[(usize, usize)]) -> NodeDependencies {
+    let mut node_dependencies = vec![Vec::new(); node_count];
+    for &(from, to) in edges {
+        node_dependencies[from].push(EdgeInfo {
+            to,
+            allowed: true,
+            label: String::new(),
+            weight: 1,
+            locations: Vec::new(),
+            types: Vec::new(),
+            merged: Vec::new(),
+        });
+    }
+    node_dependencies
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn location(line: usize) -> SourceLocation {
+        SourceLocation { file: "Shop.Web/Page.cs".to_string(), line, column: 1, end_line: line, kind: "using".to_string(), text: "using Shop.Entities;".to_string() }
+    }
+
+    fn edge(to: usize, lines: &[usize], types: &[&str], merged: &[&str]) -> EdgeInfo {
+        EdgeInfo {
+            to,
+            allowed: true,
+            label: "Shop.Web -> Shop.Entities".to_string(),
+            weight: lines.len().max(1),
+            locations: lines.iter().map(|&line| location(line)).collect(),
+            types: types.iter().map(|name| name.to_string()).collect(),
+            merged: merged.iter().map(|label| label.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn merge_counts_new_locations_only() {
+        let mut existing = edge(1, &[1, 2], &[], &[]);
+        existing.merge(edge(1, &[2], &[], &[]));
+        assert_eq!(existing.weight, 2);
+        assert_eq!(existing.locations, vec![location(1), location(2)]);
+
+        existing.merge(edge(1, &[2, 3], &[], &[]));
+        assert_eq!(existing.weight, 4);
+        assert_eq!(existing.locations, vec![location(1), location(2), location(3)]);
+    }
+
+    #[test]
+    fn merge_without_locations_adds_the_weight() {
+        let mut existing = edge(1, &[], &[], &[]);
+        existing.merge(edge(1, &[], &[], &[]));
+        assert_eq!(existing.weight, 2);
+        assert!(existing.locations.is_empty());
+    }
+
+    #[test]
+    fn merge_unions_types_and_appends_merged_labels() {
+        let mut existing = edge(1, &[1], &["Shop.Entities.Order"], &["A -> B"]);
+        existing.merge(edge(1, &[1], &["Shop.Entities.Order", "Shop.Entities.Invoice"], &["C -> D"]));
+        assert_eq!(existing.types, vec!["Shop.Entities.Order", "Shop.Entities.Invoice"]);
+        assert_eq!(existing.merged, vec!["A -> B", "C -> D"]);
+        assert_eq!(existing.label, "Shop.Web -> Shop.Entities");
+    }
+
+    #[test]
+    fn add_edge_merges_edges_to_the_same_target() {
+        let mut edges = EdgesInfo::new();
+        add_edge(&mut edges, edge(1, &[1], &[], &[]));
+        add_edge(&mut edges, edge(2, &[1], &[], &[]));
+        add_edge(&mut edges, edge(1, &[1], &[], &[]));
+        add_edge(&mut edges, edge(1, &[5], &[], &[]));
+        assert_eq!(edges.iter().map(|edge| (edge.to, edge.weight)).collect::<Vec<_>>(), vec![(1, 2), (2, 1)]);
+        assert_eq!(edges[0].locations, vec![location(1), location(5)]);
+    }
+}
@@ -0,0 +1,234 @@
+use std::collections::VecDeque;
+
+use regex::Regex;
+
+use crate::core::{node::Node, dependencies::NodeDependencies};
+
+/// Which neighbors of the focused nodes are kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FocusDirection {
+    /// Nodes depending on the focused nodes.
+    Up,
+    /// Nodes the focused nodes depend on.
+    Down,
+    #[default]
+    Both,
+}
+
+/// Filters applied to the graph before it is displayed. They run in this order: focus, layers,
+/// violations.
+#[derive(Debug, Clone, Default)]
+pub struct GraphFilter {
+    /// Keeps the nodes whose id or name matches, with their neighborhood.
+    pub focus: Option<Regex>,
+    /// Maximum distance from a focused node, unlimited when `None`.
+    pub depth: Option<usize>,
+    pub direction: FocusDirection,
+    /// Layers to keep; every layer when empty.
+    pub only_layers: Vec<String>,
+    pub exclude_layers: Vec<String>,
+    /// Keeps only the disallowed edges and their endpoints.
+    pub only_violations: bool,
+    /// Removes the nodes of the `unknown` layer.
+    pub hide_unknown: bool,
+}
+
+impl GraphFilter {
+    pub fn is_empty(&self) -> bool {
+        self.focus.is_none()
+            && self.only_layers.is_empty()
+            && self.exclude_layers.is_empty()
+            && !self.only_violations
+            && !self.hide_unknown
+    }
+}
+
+/// Returns the nodes kept by the filter, and the edges between them re-indexed accordingly.
+pub fn filter_graph(nodes: &[Node], node_dependencies: &NodeDependencies, filter: &GraphFilter) -> (Vec<Node>, NodeDependencies) {
+    let mut keep = match &filter.focus {
+        Some(focus) => neighborhood(node_dependencies, &focused_nodes(nodes, focus), filter.depth, filter.direction),
+        None => vec![true; nodes.len()],
+    };
+
+    for (index, node) in nodes.iter().enumerate() {
+        let layer_kept = (filter.only_layers.is_empty() || filter.only_layers.contains(&node.layer))
+            && !filter.exclude_layers.contains(&node.layer)
+            && !(filter.hide_unknown && node.layer == "unknown");
+        keep[index] &= layer_kept;
+    }
+
+    let kept_edge = |from: usize, to: usize, allowed: bool| keep[from] && keep[to] && !(filter.only_violations && allowed);
+    if filter.only_violations {
+        let mut endpoints = vec![false; nodes.len()];
+        for (from, edges) in node_dependencies.iter().enumerate() {
+            for edge in edges.iter().filter(|edge| kept_edge(from, edge.to, edge.allowed)) {
+                endpoints[from] = true;
+                endpoints[edge.to] = true;
+            }
+        }
+        keep = endpoints;
+    }
+
+    let (filtered_nodes, mut filtered_dependencies) = subgraph(nodes, node_dependencies, &keep);
+    if filter.only_violations {
+        for edges in &mut filtered_dependencies {
+            edges.retain(|edge| !edge.allowed);
+        }
+    }
+    (filtered_nodes, filtered_dependencies)
+}
+
+/// Indices of the nodes whose id or name matches the focus pattern.
+pub fn focused_nodes(nodes: &[Node], focus: &Regex) -> Vec<usize> {
+    (0..nodes.len())
+        .filter(|&index| focus.is_match(&nodes[index].id) || focus.is_match(&nodes[index].name))
+        .collect()
+}
+
+/// The nodes flagged in `keep` and the edges between them, re-indexed.
+pub fn subgraph(nodes: &[Node], node_dependencies: &NodeDependencies, keep: &[bool]) -> (Vec<Node>, NodeDependencies) {
+    let mut new_indices = vec![None; nodes.len()];
+    let mut kept_nodes = Vec::new();
+    for (index, node) in nodes.iter().enumerate().filter(|(index, _)| keep[*index]) {
+        new_indices[index] = Some(kept_nodes.len());
+        kept_nodes.push(node.clone());
+    }
+
+    let kept_dependencies = node_dependencies.iter().enumerate()
+        .filter(|(from, _)| keep[*from])
+        .map(|(_, edges)| {
+            edges.iter()
+                .filter_map(|edge| new_indices[edge.to].map(|to| {
+                    let mut edge = edge.clone();
+                    edge.to = to;
+                    edge
+                }))
+                .collect()
+        })
+        .collect();
+
+    (kept_nodes, kept_dependencies)
+}
+
+// Nodes within `depth` of the focused nodes, following dependencies down, dependents up, or both
+fn neighborhood(node_dependencies: &NodeDependencies, focused: &[usize], depth: Option<usize>, direction: FocusDirection) -> Vec<bool> {
+    let mut dependents = vec![Vec::new(); node_dependencies.len()];
+    let mut dependencies = vec![Vec::new(); node_dependencies.len()];
+    for (from, edges) in node_dependencies.iter().enumerate() {
+        for edge in edges {
+            dependencies[from].push(edge.to);
+            dependents[edge.to].push(from);
+        }
+    }
+
+    let mut keep = vec![false; node_dependencies.len()];
+    let walks = match direction {
+        FocusDirection::Up => vec![&dependents],
+        FocusDirection::Down => vec![&dependencies],
+        FocusDirection::Both => vec![&dependents, &dependencies],
+    };
+    for adjacency in walks {
+        let mut distances: Vec<Option<usize>> = vec![None; node_dependencies.len()];
+        let mut queue = VecDeque::new();
+        for &index in focused {
+            distances[index] = Some(0);
+            queue.push_back(index);
+        }
+        while let Some(current) = queue.pop_front() {
+            keep[current] = true;
+            let distance = distances[current].unwrap_or_default();
+            if depth.is_some_and(|depth| distance >= depth) {
+                continue;
+            }
+            for &next in &adjacency[current] {
+                if distances[next].is_none() {
+                    distances[next] = Some(distance + 1);
+                    queue.push_back(next);
+                }
+            }
+        }
+    }
+    keep
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::dependencies::test_graph;
+
+    // a -> b -> c -> d, e -> c; the edge c -> d is disallowed
+    fn graph() -> (Vec<Node>, NodeDependencies) {
+        let nodes = [("a", "io"), ("b", "usecase"), ("c", "core"), ("d", "unknown"), ("e", "io")].iter()
+            .map(|(name, layer)| Node {
+                id: format!("/repo/{}", name),
+                name: name.to_string(),
+                layer: layer.to_string(),
+                node_type: "project".to_string(),
+                color: String::new(),
+                group: None,
+            })
+            .collect();
+        let mut node_dependencies = test_graph(5, &[(0, 1), (1, 2), (2, 3), (4, 2)]);
+        node_dependencies[2][0].allowed = false;
+        (nodes, node_dependencies)
+    }
+
+    fn names(nodes: &[Node]) -> Vec<&str> {
+        nodes.iter().map(|node| node.name.as_str()).collect()
+    }
+
+    fn focus(pattern: &str, depth: Option<usize>, direction: FocusDirection) -> GraphFilter {
+        GraphFilter { focus: Some(Regex::new(pattern).unwrap()), depth, direction, ..GraphFilter::default() }
+    }
+
+    #[test]
+    fn focus_follows_depth_and_direction() {
+        let (nodes, node_dependencies) = graph();
+        let kept = |filter: &GraphFilter| names(&filter_graph(&nodes, &node_dependencies, filter).0).join("");
+        assert_eq!(kept(&focus("^c$", None, FocusDirection::Both)), "abcde");
+        assert_eq!(kept(&focus("^c$", Some(1), FocusDirection::Both)), "bcde");
+        assert_eq!(kept(&focus("^c$", Some(1), FocusDirection::Up)), "bce");
+        assert_eq!(kept(&focus("^c$", None, FocusDirection::Down)), "cd");
+        assert_eq!(kept(&focus("^a$", Some(0), FocusDirection::Both)), "a");
+        // Ids match too
+        assert_eq!(kept(&focus("/repo/d", None, FocusDirection::Up)), "abcde");
+    }
+
+    #[test]
+    fn focus_matching_nothing_keeps_nothing() {
+        let (nodes, node_dependencies) = graph();
+        let filter = focus("nothing", None, FocusDirection::Both);
+        assert!(focused_nodes(&nodes, filter.focus.as_ref().unwrap()).is_empty());
+        let (kept_nodes, kept_dependencies) = filter_graph(&nodes, &node_dependencies, &filter);
+        assert!(kept_nodes.is_empty() && kept_dependencies.is_empty());
+    }
+
+    #[test]
+    fn layer_filters_keep_the_edges_between_kept_nodes() {
+        let (nodes, node_dependencies) = graph();
+        let filter = GraphFilter { only_layers: vec!["io".to_string(), "usecase".to_string()], ..GraphFilter::default() };
+        let (kept_nodes, kept_dependencies) = filter_graph(&nodes, &node_dependencies, &filter);
+        assert_eq!(names(&kept_nodes), ["a", "b", "e"]);
+        assert_eq!(kept_dependencies.iter().map(|edges| edges.iter().map(|edge| edge.to).collect()).collect::<Vec<Vec<usize>>>(), [vec![1], vec![], vec![]]);
+
+        let filter = GraphFilter { exclude_layers: vec!["io".to_string()], hide_unknown: true, ..GraphFilter::default() };
+        let (kept_nodes, kept_dependencies) = filter_graph(&nodes, &node_dependencies, &filter);
+        assert_eq!(names(&kept_nodes), ["b", "c"]);
+        assert_eq!(kept_dependencies[0][0].to, 1);
+        assert!(kept_dependencies[1].is_empty());
+    }
+
+    #[test]
+    fn only_violations_keeps_disallowed_edges_and_their_endpoints() {
+        let (nodes, node_dependencies) = graph();
+        let filter = GraphFilter { only_violations: true, ..GraphFilter::default() };
+        let (kept_nodes, kept_dependencies) = filter_graph(&nodes, &node_dependencies, &filter);
+        assert_eq!(names(&kept_nodes), ["c", "d"]);
+        assert_eq!(kept_dependencies.iter().map(Vec::len).collect::<Vec<_>>(), [1, 0]);
+        assert!(!kept_dependencies[0][0].allowed);
+
+        // Filters run before: hiding the unknown layer removes the only violation
+        let filter = GraphFilter { only_violations: true, hide_unknown: true, ..GraphFilter::default() };
+        assert!(filter_graph(&nodes, &node_dependencies, &filter).0.is_empty());
+    }
+}
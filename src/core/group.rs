@@ -0,0 +1,134 @@
+
+use crate::core::dependencies::{EdgeInfo, NodeDependencies};
+use crate::core::node::Node;
+
+/// How nodes are merged into a coarser graph.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GroupBy {
+    /// One node per layer.
+    Layer,
+}
+
+/// Merges the nodes of every layer into a single `layer` node, in order of appearance.
+pub fn group_by_layer(nodes: &[Node], node_dependencies: &NodeDependencies) -> (Vec<Node>, NodeDependencies) {
+    let mut groups: Vec<Node> = Vec::new();
+    let mut group_of = Vec::with_capacity(nodes.len());
+    for node in nodes {
+        let index = match groups.iter().position(|group| group.id == node.layer) {
+            Some(index) => index,
+            None => {
+                groups.push(Node {
+                    id: node.layer.clone(),
+                    name: node.layer.clone(),
+                    layer: node.layer.clone(),
+                    node_type: "layer".to_string(),
+                    color: node.color.clone(),
+                    group: None,
+                });
+                groups.len() - 1
+            }
+        };
+        group_of.push(index);
+    }
+
+    let group_dependencies = merge_edges(&groups, node_dependencies, &group_of);
+    (groups, group_dependencies)
+}
+
+/// Aggregates the edges of nodes merged into groups, `group_of` giving the group of every node.
+/// The weight of an aggregated edge is the number of edges behind it, listed in `merged`, and
+/// the edge is disallowed as soon as one of them is.
+///
+/// Unlike `collapse_namespaces`, edges inside a group are kept as a self-loop: layer rules also
+/// cover dependencies within a layer, so these edges may be violations.
+pub fn merge_edges(groups: &[Node], node_dependencies: &NodeDependencies, group_of: &[usize]) -> NodeDependencies {
+    let mut group_dependencies: NodeDependencies = vec![Vec::new(); groups.len()];
+    for (from, edges) in node_dependencies.iter().enumerate() {
+        let from_group = group_of[from];
+        for edge in edges {
+            let to_group = group_of[edge.to];
+            match group_dependencies[from_group].iter_mut().find(|existing| existing.to == to_group) {
+                Some(existing) => {
+                    existing.allowed &= edge.allowed;
+                    existing.weight += 1;
+                    existing.locations.extend(edge.locations.iter().cloned());
+                    for type_name in &edge.types {
+                        if !existing.types.contains(type_name) {
+                            existing.types.push(type_name.clone());
+                        }
+                    }
+                    existing.merged.push(edge.label.clone());
+                }
+                None => group_dependencies[from_group].push(EdgeInfo {
+                    to: to_group,
+                    allowed: edge.allowed,
+                    label: format!("{} -> {}", groups[from_group].name, groups[to_group].name),
+                    weight: 1,
+                    locations: edge.locations.clone(),
+                    types: edge.types.clone(),
+                    merged: vec![edge.label.clone()],
+                }),
+            }
+        }
+    }
+    group_dependencies
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::dependencies::{test_graph, SourceLocation};
+
+    fn node(name: &str, layer: &str) -> Node {
+        Node {
+            id: name.to_string(),
+            name: name.to_string(),
+            layer: layer.to_string(),
+            node_type: "project".to_string(),
+            color: String::new(),
+            group: None,
+        }
+    }
+
+    fn location(line: usize) -> SourceLocation {
+        SourceLocation { file: "a.csproj".to_string(), line, column: 1, end_line: line, kind: "ProjectReference".to_string(), text: String::new() }
+    }
+
+    #[test]
+    fn layer_edges_count_the_node_edges_behind_them() {
+        let nodes = [node("a", "io"), node("b", "io"), node("c", "core"), node("d", "core")];
+        let mut node_dependencies = test_graph(4, &[(0, 2), (1, 2), (1, 3), (0, 1), (2, 3)]);
+        for (from, edges) in node_dependencies.iter_mut().enumerate() {
+            for edge in edges {
+                edge.label = format!("{} -> {}", nodes[from].name, nodes[edge.to].name);
+                edge.weight = 3;
+                edge.locations = vec![location(from + 1)];
+                edge.types = vec![format!("T{}", edge.to)];
+            }
+        }
+        node_dependencies[1][1].allowed = false;
+
+        let (groups, group_dependencies) = group_by_layer(&nodes, &node_dependencies);
+        assert_eq!(groups.iter().map(|group| group.id.as_str()).collect::<Vec<_>>(), ["io", "core"]);
+
+        let io_to_core = group_dependencies[0].iter().find(|edge| edge.to == 1).unwrap();
+        // Three node edges, whatever their own weight; one of them is disallowed
+        assert_eq!(io_to_core.weight, 3);
+        assert!(!io_to_core.allowed);
+        assert_eq!(io_to_core.label, "io -> core");
+        assert_eq!(io_to_core.merged, ["a -> c", "b -> c", "b -> d"]);
+        assert_eq!(io_to_core.locations, [location(1), location(2), location(2)]);
+        assert_eq!(io_to_core.types, ["T2", "T3"]);
+    }
+
+    #[test]
+    fn edges_inside_a_layer_become_self_loops() {
+        let nodes = [node("a", "io"), node("b", "io"), node("c", "core"), node("d", "core")];
+        let mut node_dependencies = test_graph(4, &[(0, 1), (2, 3), (3, 2)]);
+        node_dependencies[2][0].allowed = false;
+
+        let (_, group_dependencies) = group_by_layer(&nodes, &node_dependencies);
+        assert_eq!(group_dependencies[0].iter().map(|edge| (edge.to, edge.weight, edge.allowed)).collect::<Vec<_>>(), [(0, 1, true)]);
+        assert_eq!(group_dependencies[1].iter().map(|edge| (edge.to, edge.weight, edge.allowed)).collect::<Vec<_>>(), [(1, 2, false)]);
+    }
+}
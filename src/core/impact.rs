@@ -0,0 +1,76 @@
+use std::collections::VecDeque;
+
+use crate::core::dependencies::NodeDependencies;
+
+/// A node affected by a change, with the length of its shortest dependency path to a changed node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImpactedNode {
+    pub index: usize,
+    /// 0 for the changed nodes themselves.
+    pub distance: usize,
+}
+
+/// Every node depending on one of the `changed` nodes, directly or transitively, found by
+/// walking the dependencies in reverse. Sorted by distance, then by index.
+pub fn impacted_nodes(node_dependencies: &NodeDependencies, changed: &[usize]) -> Vec<ImpactedNode> {
+    let mut dependents = vec![Vec::new(); node_dependencies.len()];
+    for (from, edges) in node_dependencies.iter().enumerate() {
+        for edge in edges.iter().filter(|edge| edge.to != from) {
+            dependents[edge.to].push(from);
+        }
+    }
+
+    let mut distances: Vec<Option<usize>> = vec![None; node_dependencies.len()];
+    let mut queue = VecDeque::new();
+    for &index in changed {
+        if distances[index].is_none() {
+            distances[index] = Some(0);
+            queue.push_back(index);
+        }
+    }
+    while let Some(current) = queue.pop_front() {
+        let distance = distances[current].unwrap_or_default() + 1;
+        for &dependent in &dependents[current] {
+            if distances[dependent].is_none() {
+                distances[dependent] = Some(distance);
+                queue.push_back(dependent);
+            }
+        }
+    }
+
+    let mut impacted: Vec<ImpactedNode> = distances.into_iter().enumerate()
+        .filter_map(|(index, distance)| distance.map(|distance| ImpactedNode { index, distance }))
+        .collect();
+    impacted.sort_by_key(|node| (node.distance, node.index));
+    impacted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::dependencies::test_graph;
+
+    fn impacted(node_dependencies: &NodeDependencies, changed: &[usize]) -> Vec<(usize, usize)> {
+        impacted_nodes(node_dependencies, changed).into_iter().map(|node| (node.index, node.distance)).collect()
+    }
+
+    #[test]
+    fn dependents_get_their_shortest_distance() {
+        // 0 -> 1 -> 2 -> 3, 0 -> 3, 4 -> 2, 5 does not depend on 3
+        let graph = test_graph(6, &[(0, 1), (1, 2), (2, 3), (0, 3), (4, 2), (3, 5)]);
+        assert_eq!(impacted(&graph, &[3]), [(3, 0), (0, 1), (2, 1), (1, 2), (4, 2)]);
+    }
+
+    #[test]
+    fn several_changed_nodes_are_searched_together() {
+        let graph = test_graph(4, &[(0, 1), (1, 2), (3, 2)]);
+        assert_eq!(impacted(&graph, &[2, 1, 2]), [(1, 0), (2, 0), (0, 1), (3, 1)]);
+        assert!(impacted(&graph, &[]).is_empty());
+    }
+
+    #[test]
+    fn cycles_and_self_dependencies_terminate() {
+        let graph = test_graph(3, &[(0, 1), (1, 0), (1, 1), (2, 0)]);
+        assert_eq!(impacted(&graph, &[0]), [(0, 0), (1, 1), (2, 1)]);
+    }
+}
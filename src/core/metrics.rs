@@ -0,0 +1,130 @@
+use serde::Serialize;
+
+use crate::core::{node::Node, dependencies::NodeDependencies};
+
+/// Types declared in a node (namespace or project), used for its abstractness.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TypeCounts {
+    pub types: usize,
+    /// Interfaces and abstract classes or records.
+    pub abstract_types: usize,
+}
+
+impl TypeCounts {
+    pub fn abstractness(&self) -> Option<f64> {
+        (self.types > 0).then(|| self.abstract_types as f64 / self.types as f64)
+    }
+}
+
+/// Robert C. Martin's package metrics of a node.
+#[derive(Debug, Clone, Serialize)]
+pub struct NodeMetrics {
+    pub id: String,
+    pub name: String,
+    pub layer: String,
+    /// Afferent coupling (Ca): number of nodes depending on this one.
+    pub afferent: usize,
+    /// Efferent coupling (Ce): number of nodes this one depends on.
+    pub efferent: usize,
+    /// Ce / (Ca + Ce), from 0 (stable) to 1 (unstable). Nodes without dependencies are stable.
+    pub instability: f64,
+    /// Abstract types / types (A). `None` when the node declares no types, e.g. packages or
+    /// external namespaces.
+    pub abstractness: Option<f64>,
+    /// Distance from the main sequence, |A + I - 1|.
+    pub distance: Option<f64>,
+}
+
+pub fn instability(afferent: usize, efferent: usize) -> f64 {
+    if afferent + efferent == 0 {
+        0.0
+    } else {
+        efferent as f64 / (afferent + efferent) as f64
+    }
+}
+
+/// Fan-in and fan-out of every node, ignoring self-dependencies.
+pub fn coupling(node_dependencies: &NodeDependencies) -> Vec<(usize, usize)> {
+    let mut coupling = vec![(0, 0); node_dependencies.len()];
+    for (from, edges) in node_dependencies.iter().enumerate() {
+        for edge in edges.iter().filter(|edge| edge.to != from) {
+            coupling[from].1 += 1;
+            coupling[edge.to].0 += 1;
+        }
+    }
+    coupling
+}
+
+/// Computes the metrics of every node. `type_counts` is indexed like `nodes`.
+pub fn compute_metrics(nodes: &[Node], node_dependencies: &NodeDependencies, type_counts: &[Option<TypeCounts>]) -> Vec<NodeMetrics> {
+    coupling(node_dependencies).into_iter().enumerate()
+        .map(|(index, (afferent, efferent))| {
+            let instability = instability(afferent, efferent);
+            let abstractness = type_counts.get(index).copied().flatten().and_then(|counts| counts.abstractness());
+            NodeMetrics {
+                id: nodes[index].id.clone(),
+                name: nodes[index].name.clone(),
+                layer: nodes[index].layer.clone(),
+                afferent,
+                efferent,
+                instability,
+                abstractness,
+                distance: abstractness.map(|abstractness| (abstractness + instability - 1.0).abs()),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::dependencies::test_graph;
+
+    fn nodes(count: usize) -> Vec<Node> {
+        (0..count)
+            .map(|index| Node {
+                id: format!("N{}", index),
+                name: format!("N{}", index),
+                layer: "layer".to_string(),
+                node_type: "namespace".to_string(),
+                color: String::new(),
+                group: None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn coupling_ignores_self_dependencies() {
+        let graph = test_graph(3, &[(0, 1), (0, 2), (1, 2), (2, 2)]);
+        assert_eq!(coupling(&graph), [(0, 2), (1, 1), (2, 0)]);
+    }
+
+    #[test]
+    fn instability_of_isolated_nodes_is_zero() {
+        assert_eq!(instability(0, 0), 0.0);
+        assert_eq!(instability(3, 0), 0.0);
+        assert_eq!(instability(0, 2), 1.0);
+        assert_eq!(instability(1, 3), 0.75);
+    }
+
+    #[test]
+    fn abstractness_needs_declared_types() {
+        assert_eq!(TypeCounts { types: 0, abstract_types: 0 }.abstractness(), None);
+        assert_eq!(TypeCounts { types: 4, abstract_types: 1 }.abstractness(), Some(0.25));
+    }
+
+    #[test]
+    fn distance_is_computed_when_abstractness_is_known() {
+        let graph = test_graph(3, &[(0, 1), (0, 2), (1, 2)]);
+        let type_counts = [Some(TypeCounts { types: 2, abstract_types: 0 }), None, Some(TypeCounts { types: 4, abstract_types: 4 })];
+        let metrics = compute_metrics(&nodes(3), &graph, &type_counts);
+        let values: Vec<_> = metrics.iter()
+            .map(|m| (m.id.as_str(), m.afferent, m.efferent, m.instability, m.abstractness, m.distance))
+            .collect();
+        assert_eq!(values, [
+            ("N0", 0, 2, 1.0, Some(0.0), Some(0.0)),
+            ("N1", 1, 1, 0.5, None, None),
+            ("N2", 2, 0, 0.0, Some(1.0), Some(0.0)),
+        ]);
+    }
+}
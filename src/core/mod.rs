@@ -1,3 +1,10 @@
 pub mod node;
 pub mod dependencies;
 pub mod analysis;
+pub mod check;
+pub mod baseline;
+pub mod metrics;
+pub mod build_order;
+pub mod impact;
+pub mod filter;
+pub mod group;
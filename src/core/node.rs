@@ -1,8 +1,24 @@
-#[derive(Debug)]
+use std::path::Path;
+use path_slash::PathExt;
+
+#[derive(Debug, Clone)]
 pub struct Node {
     pub id: String,
     pub name: String,
     pub layer: String,
     pub node_type: String,
     pub color: String,
+    /// Optional grouping metadata, e.g. the solution folder containing a project.
+    pub group: Option<String>,
+}
+
+impl Node {
+    /// Id that does not depend on where the repository is checked out: paths under `root_path`
+    /// become relative with forward slashes, other ids (e.g. namespaces) are kept as they are.
+    pub fn stable_id(&self, root_path: &Path) -> String {
+        match Path::new(&self.id).strip_prefix(root_path) {
+            Ok(relative) => relative.to_slash_lossy().into_owned(),
+            Err(_) => self.id.clone(),
+        }
+    }
 }
@@ -1,22 +1,33 @@
-use std::{collections::HashMap, fs::File, path::PathBuf};
+use std::{collections::{HashMap, HashSet}, fs::File, path::{Path, PathBuf}};
 use std::env;
-use clap::{Parser, ValueEnum};
+use std::io::{self, Write};
+use clap::{ArgGroup, Parser, Subcommand, ValueEnum};
+use path_slash::PathExt;
+use regex::Regex;
 use serde_json::{self, to_writer_pretty};
 
-mod core;
-mod config;
-mod analyzers;
-mod output;
-mod utils;
-
-use config::loader::load_config;
-use config::types::Config;
-use analyzers::csharp::namespace::NamespaceDependencyManager;
-use core::analysis::{detect_cycles, GraphDependencies};
-use core::node::Node;
-use core::dependencies::NodeDependencies;
-use analyzers::csharp::project::ProjectDependencyManager;
-use output::static_output::{generate_html_output, generate_mermaid_diagram, generate_graphviz_diagram, display_graph_information};
+use depscop::config;
+use depscop::config::loader::load_config;
+use depscop::config::types::Config;
+use depscop::analyzers::csharp::namespace::{truncate_namespace, NamespaceDependencyManager, NamespaceScan, NAMESPACE_NODE_TYPE};
+use depscop::core::analysis::{detect_cycles, GraphDependencies};
+use depscop::core::build_order::build_order;
+use depscop::core::baseline::{Baseline, BASELINE_FILE_NAME};
+use depscop::core::check::check_dependencies;
+use depscop::core::filter::{filter_graph, focused_nodes, subgraph, FocusDirection, GraphFilter};
+use depscop::core::group::{group_by_layer, GroupBy};
+use depscop::core::impact::impacted_nodes;
+use depscop::core::node::Node;
+use depscop::core::dependencies::{EdgeInfo, NodeDependencies};
+use depscop::analyzers::csharp::combined::{CombinedDependencyManager, ReferenceLeak};
+use depscop::analyzers::csharp::package::PackageDependencyManager;
+use depscop::analyzers::csharp::project::{ProjectDependencyManager, PROJECT_NODE_TYPE};
+use depscop::output::diff::{diff_reports, display_diff_markdown, generate_diff_graphviz, generate_diff_mermaid, load_report};
+use depscop::output::json::{build_json_report, write_json_output, JsonReport, JsonReportOptions};
+use depscop::output::metrics::{display_metrics, write_metrics_file};
+use depscop::core::metrics::{compute_metrics, TypeCounts};
+use depscop::output::sarif::build_sarif_report;
+use depscop::output::static_output::{generate_html_output, DrillDown, HtmlOptions, generate_mermaid_diagram, generate_graphviz_diagram, display_graph_information, display_cycles, display_build_order, display_check_summary, display_impact, display_reference_report, display_fix_suggestions};
 
 #[derive(Parser)]
 #[command(
@@ -25,16 +36,28 @@ use output::static_output::{generate_html_output, generate_mermaid_diagram, gene
     author = "tecnocrata",
     about = "Analyzes dependencies from project files",
     long_about = None,
-    arg_required_else_help = true
+    arg_required_else_help = true,
+    subcommand_negates_reqs = true
 )]
+#[command(group(ArgGroup::new("source").args(["path", "solution"]).multiple(true)))]
 struct Cli {
     /// Sets a custom folder path
     #[arg(
         short = 'f',
         long = "folder",
         value_name = "PATH",
+        required_unless_present = "solution"
     )]
-    path: String,
+    path: Option<String>,
+
+    /// Solution file used as the analysis root
+    #[arg(
+        short = 's',
+        long,
+        value_name = "PATH",
+        help = "Uses the projects listed in a .sln or .slnx file instead of every .csproj under the folder (defaults the folder to the solution directory)"
+    )]
+    solution: Option<String>,
 
     /// Type of analysis to perform
     #[arg(
@@ -42,10 +65,56 @@ struct Cli {
         long,
         value_name = "TYPE",
         default_value = "csharp:projects",
-        requires = "path"
+        requires = "source"
     )]
     analysis: String,
 
+    /// MSBuild property overrides
+    #[arg(
+        short = 'p',
+        long = "property",
+        value_name = "NAME=VALUE",
+        value_parser = parse_property,
+        help = "Sets an MSBuild global property used when evaluating project files (repeatable, overrides 'csharp.properties')"
+    )]
+    properties: Vec<(String, String)>,
+
+    /// Scan code for type references
+    #[arg(
+        long = "type-references",
+        help = "With 'csharp:namespaces', also adds edges for types referenced in code (qualified or by simple name), weighted by the number of references"
+    )]
+    type_references: bool,
+
+    /// Print coupling metrics
+    #[arg(
+        long,
+        help = "Prints afferent/efferent coupling, instability, abstractness and distance from the main sequence for every node, and adds an A/I chart to the HTML output"
+    )]
+    metrics: bool,
+
+    /// Metrics export file
+    #[arg(
+        long = "metrics-file",
+        value_name = "PATH",
+        help = "Writes the metrics to a .csv or .json file"
+    )]
+    metrics_file: Option<String>,
+
+    /// Print the lines to delete for unused project references
+    #[arg(
+        long = "fix-suggestions",
+        help = "Lists the ProjectReference lines to delete for references whose project declares no namespace or type used by the referencing project"
+    )]
+    fix_suggestions: bool,
+
+    /// Compare project references with the namespaces used by the code
+    #[arg(
+        long = "reference-report",
+        help = "Lists projects whose namespaces are used without a direct ProjectReference, and ProjectReferences whose namespaces are never used"
+    )]
+    reference_report: bool,
+
     /// Generate configuration file
     #[arg(
         short = 'g',
@@ -68,10 +137,42 @@ struct Cli {
         short = 'c',
         long = "detect-cycles",
         help = "Detects cycles in project dependencies",
-        requires = "path"
+        requires = "source"
     )]
     detect_cycles: bool,
 
+    /// Print the build order
+    #[arg(
+        long = "build-order",
+        help = "Prints a topological build order grouped into waves that can build in parallel, with the critical path; cycles are built as a single step",
+        requires = "source"
+    )]
+    build_order: bool,
+
+    /// Fail on rule violations
+    #[arg(
+        long,
+        help = "Exits with a non-zero status when disallowed dependencies (2), cycles (4), unknown nodes (8) or unstable dependencies (16) are found; codes are combined",
+        requires = "source"
+    )]
+    check: bool,
+
+    /// Baseline file with accepted violations
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "Baseline file of accepted disallowed dependencies (default: depscop-baseline.json in the analyzed folder)"
+    )]
+    baseline: Option<String>,
+
+    /// Write the current violations to the baseline file
+    #[arg(
+        long = "generate-baseline",
+        help = "Writes the current disallowed dependencies to the baseline file",
+        requires = "source"
+    )]
+    generate_baseline: bool,
+
     /// Output file path for HTML
     #[arg(
         long = "output-html",
@@ -81,15 +182,167 @@ struct Cli {
     )]
     output_html: Option<String>,
 
-    /// Output format (mermaid, graphviz, d3)
+    /// Output format (mermaid, graphviz, d3, json, sarif)
     #[arg(
         short,
         long,
         value_name = "FORMAT",
-        help = "Selects output format ('d3', 'mermaid' or 'graphviz')",
-        requires = "path"
+        help = "Selects output format ('d3', 'mermaid', 'graphviz', 'json' or 'sarif')",
+        requires = "source"
     )]
     output: Option<String>,
+
+    /// Output file path for machine-readable formats
+    #[arg(
+        long = "output-file",
+        value_name = "PATH",
+        help = "Writes the 'json' or 'sarif' output to the specified file instead of stdout",
+        requires = "output"
+    )]
+    output_file: Option<String>,
+
+    /// Keep the nodes matching a pattern and their neighborhood
+    #[arg(
+        long,
+        value_name = "PATTERN",
+        help = "Only shows the nodes whose id or name matches the regular expression, with the nodes they depend on and the nodes depending on them",
+        requires = "source"
+    )]
+    focus: Option<String>,
+
+    /// Neighborhood depth around the focused nodes
+    #[arg(
+        long,
+        value_name = "N",
+        help = "Limits --focus to the nodes at most N dependencies away (default: unlimited)",
+        requires = "focus"
+    )]
+    depth: Option<usize>,
+
+    /// Neighborhood direction around the focused nodes
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = Direction::Both,
+        help = "Follows --focus up to the dependents, down to the dependencies, or both",
+        requires = "focus"
+    )]
+    direction: Direction,
+
+    /// Layers to show
+    #[arg(
+        long = "only-layer",
+        value_name = "LAYERS",
+        value_delimiter = ',',
+        help = "Only shows the nodes of the given layers (comma-separated or repeated)"
+    )]
+    only_layers: Vec<String>,
+
+    /// Layers to hide
+    #[arg(
+        long = "exclude-layer",
+        value_name = "LAYERS",
+        value_delimiter = ',',
+        help = "Hides the nodes of the given layers (comma-separated or repeated)"
+    )]
+    exclude_layers: Vec<String>,
+
+    /// Show disallowed edges only
+    #[arg(
+        long = "only-violations",
+        help = "Only shows the disallowed dependencies and the nodes they connect"
+    )]
+    only_violations: bool,
+
+    /// Hide nodes outside of every layer
+    #[arg(
+        long = "hide-unknown",
+        help = "Hides the nodes of the 'unknown' layer"
+    )]
+    hide_unknown: bool,
+
+    /// Truncate namespaces to their first segments
+    #[arg(
+        long = "collapse-namespaces",
+        value_name = "N",
+        help = "Merges the namespaces sharing their first N segments into one node; the HTML output expands them on click",
+        requires = "source"
+    )]
+    collapse_namespaces: Option<usize>,
+
+    /// Merge nodes into a coarser graph
+    #[arg(
+        long = "group-by",
+        value_enum,
+        value_name = "GROUPING",
+        help = "Merges the displayed nodes: 'layer' shows one node per layer, with the number of node dependencies between layers",
+        requires = "source"
+    )]
+    group_by: Option<Grouping>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Lists every node that depends on a node, directly or transitively
+    Impact {
+        /// Changed node
+        #[arg(
+            value_name = "NODE",
+            required_unless_present = "changed_files",
+            help = "Id or name of the changed node (project path or file name, namespace name...)"
+        )]
+        node: Option<String>,
+
+        /// Read changed files from stdin
+        #[arg(
+            long = "changed-files",
+            help = "Reads changed file paths from stdin, one per line (e.g. from 'git diff --name-only'), and uses their owning projects or namespaces as the changed nodes; relative paths are resolved against the root of the git repository"
+        )]
+        changed_files: bool,
+
+        /// Print node ids only
+        #[arg(
+            long,
+            help = "Prints only the ids of the changed and affected nodes, one per line"
+        )]
+        ids: bool,
+    },
+    /// Lists the nodes, dependencies and violations added or removed between two graphs
+    Diff {
+        /// Old JSON report
+        #[arg(
+            value_name = "OLD",
+            required_unless_present = "against",
+            requires = "new",
+            help = "JSON report of the old graph, written by '--output json'"
+        )]
+        old: Option<String>,
+
+        /// New JSON report
+        #[arg(value_name = "NEW", help = "JSON report of the new graph")]
+        new: Option<String>,
+
+        /// Git revision to compare with
+        #[arg(
+            long,
+            value_name = "GIT_REV",
+            conflicts_with_all = ["old", "new"],
+            help = "Compares the analyzed folder with the given git revision, checked out in a temporary git worktree"
+        )]
+        against: Option<String>,
+
+        /// Output format
+        #[arg(
+            long,
+            value_enum,
+            default_value_t = DiffFormat::Markdown,
+            help = "Renders the changes as a Markdown summary, or as a Mermaid or Graphviz diagram with added edges in green and removed edges in grey"
+        )]
+        format: DiffFormat,
+    },
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, ValueEnum)]
@@ -98,6 +351,38 @@ enum OutputFormat {
     D3,
 }
 
+// Values of --direction, mapped to `FocusDirection`
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum)]
+enum Direction {
+    /// Nodes depending on the focused nodes
+    Up,
+    /// Nodes the focused nodes depend on
+    Down,
+    Both,
+}
+
+// Values of --group-by, mapped to `GroupBy`
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum)]
+enum Grouping {
+    /// One node per layer
+    Layer,
+}
+
+// Values of `diff --format`
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum)]
+enum DiffFormat {
+    Markdown,
+    Mermaid,
+    Graphviz,
+}
+
+fn parse_property(value: &str) -> Result<(String, String), String> {
+    match value.split_once('=') {
+        Some((name, value)) if !name.trim().is_empty() => Ok((name.trim().to_string(), value.to_string())),
+        _ => Err(format!("expected NAME=VALUE, got '{}'", value)),
+    }
+}
+
 // Main entry point of the application
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let cli = Cli::parse();
@@ -105,7 +390,16 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Get the current directory
     let current_dir = env::current_dir()?;
 
-    let root_path = PathBuf::from(&cli.path);
+    let solution_path = match &cli.solution {
+        Some(solution) => Some(current_dir.join(solution).canonicalize()?),
+        None => None,
+    };
+
+    let root_path = match (&cli.path, &solution_path) {
+        (Some(path), _) => PathBuf::from(path),
+        (None, Some(solution)) => solution.parent().unwrap_or(&current_dir).to_path_buf(),
+        (None, None) => current_dir.clone(),
+    };
     let complete_path = if root_path.is_relative() {
         current_dir.join(root_path)
     } else {
@@ -120,41 +414,148 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         return Ok(());
     }
 
-    let config = load_config(&root_path);
+    let config = cli_config(&cli, &root_path);
 
     let analysis = cli.analysis.as_str();
     let layers: Vec<Node> = get_layers(&config);
     let layer_dependencies: NodeDependencies = get_layer_dependencies (&layers, &config.global.rules);
 
-    let result = match analysis {
+    let result = match &cli.command {
+        Some(Command::Diff { old, new, against, format }) => {
+            let reports = match against {
+                Some(revision) => revision_reports(&cli, revision, &root_path, solution_path.as_deref(), &config),
+                None => load_reports(old.as_deref().unwrap_or_default(), new.as_deref().unwrap_or_default()),
+            };
+            reports.and_then(|(old_report, new_report)| display_diff(&old_report, &new_report, *format))
+        }
+        _ => analyze(analysis, &root_path, solution_path.as_deref(), &config)
+            .and_then(|result| generate_output(&cli, &root_path, &result, &layers, &layer_dependencies, &config)),
+    };
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+// Configuration of the analyzed folder, with the overrides given on the command line
+fn cli_config(cli: &Cli, root_path: &Path) -> Config {
+    let mut config = load_config(root_path);
+    if let Some(csharp) = config.csharp.as_mut() {
+        csharp.properties.extend(cli.properties.iter().cloned());
+        csharp.type_references |= cli.type_references;
+    }
+    config
+}
+
+// Graph of an analysis, with the scan of the C# code for the analyses reading it
+struct AnalysisResult {
+    nodes: Vec<Node>,
+    dependencies: NodeDependencies,
+    scan: Option<NamespaceScan>,
+}
+
+// Nodes and dependencies of the selected analysis
+fn analyze(analysis: &str, root_path: &Path, solution_path: Option<&Path>, config: &Config) -> Result<AnalysisResult, Box<dyn std::error::Error>> {
+    match analysis {
         "csharp:projects" => {
-            let nodes = ProjectDependencyManager::collect_nodes(&root_path, &config)?;
-            let project_dependencies = ProjectDependencyManager::find_dependencies(&nodes, &config)?;
+            let nodes = collect_project_nodes(root_path, solution_path, config)?;
+            let project_dependencies = ProjectDependencyManager::find_dependencies(&nodes, config)?;
 
-            generate_output(&cli, &nodes, &project_dependencies, &layers, &layer_dependencies, &config)
+            Ok(AnalysisResult { nodes, dependencies: project_dependencies, scan: None })
+        }
+        "csharp:packages" => {
+            let projects = collect_project_nodes(root_path, solution_path, config)?;
+            let evaluated = ProjectDependencyManager::evaluate_projects(&projects, config)?;
+            let nodes = PackageDependencyManager::add_package_nodes(projects, &evaluated, config);
+            let package_dependencies = PackageDependencyManager::package_dependencies(&nodes, &evaluated, config);
+
+            Ok(AnalysisResult { nodes, dependencies: package_dependencies, scan: None })
         }
         "csharp:namespaces" => {
-            let nodes = NamespaceDependencyManager::collect_nodes(&root_path, &config)?;
-            let namespace_dependencies = NamespaceDependencyManager::find_dependencies(&root_path, &nodes, &config)?;
+            if solution_path.is_some() {
+                eprintln!("Warning: --solution only applies to 'csharp:projects', 'csharp:packages' and 'csharp:all'; scanning the folder instead.");
+            }
+            let scan = NamespaceDependencyManager::scan(root_path, config)?;
+            for warning in NamespaceDependencyManager::warnings(&scan) {
+                eprintln!("Warning: {}", warning);
+            }
+            let nodes = NamespaceDependencyManager::collect_nodes(&scan, config)?;
+            let namespace_dependencies = NamespaceDependencyManager::find_dependencies(&scan, &nodes, config)?;
 
-            generate_output(&cli, &nodes, &namespace_dependencies, &layers, &layer_dependencies, &config)
+            Ok(AnalysisResult { nodes, dependencies: namespace_dependencies, scan: Some(scan) })
+        }
+        "csharp:all" => {
+            let projects = collect_project_nodes(root_path, solution_path, config)?;
+            let scan = NamespaceDependencyManager::scan(root_path, config)?;
+            let nodes = CombinedDependencyManager::add_namespace_nodes(projects, &scan, config)?;
+            let combined_dependencies = CombinedDependencyManager::find_dependencies(&scan, &nodes, config)?;
+            for leak in CombinedDependencyManager::find_reference_leaks(&nodes, &combined_dependencies) {
+                display_reference_leak(&nodes, &combined_dependencies, &leak);
+            }
+
+            Ok(AnalysisResult { nodes, dependencies: combined_dependencies, scan: Some(scan) })
         }
         _ => {
-            eprintln!("Unsupported analysis type. Please specify 'csharp:projects', 'csharp:namespaces', or 'javascript:folders'.");
+            eprintln!("Unsupported analysis type. Please specify 'csharp:projects', 'csharp:packages', 'csharp:namespaces', 'csharp:all', or 'javascript:folders'.");
             Err(Box::from("Unsupported analysis type"))
         }
-    };
+    }
+}
 
-    match result {
-        Ok(_) => Ok(()),
-        Err(e) => {
-            eprintln!("Error: {}", e);
-            std::process::exit(1);
+// Scan of the analysis, or a new scan stored in `scanned` for analyses that do not read the code
+fn namespace_scan<'a>(result: &'a AnalysisResult, scanned: &'a mut Option<NamespaceScan>, root_path: &Path, config: &Config) -> Result<&'a NamespaceScan, io::Error> {
+    match &result.scan {
+        Some(scan) => Ok(scan),
+        None => Ok(scanned.insert(NamespaceDependencyManager::scan(root_path, config)?)),
+    }
+}
+
+fn collect_project_nodes(root_path: &Path, solution_path: Option<&Path>, config: &Config) -> Result<Vec<Node>, Box<dyn std::error::Error>> {
+    match solution_path {
+        Some(solution) => {
+            let (nodes, missing) = ProjectDependencyManager::collect_solution_nodes(solution, config)?;
+            for project in &missing {
+                eprintln!("Project listed in solution but missing on disk: {}", project.path.display());
+            }
+            Ok(nodes)
         }
+        None => Ok(ProjectDependencyManager::collect_nodes(root_path, config)?),
     }
 }
 
-fn get_layer_dependencies(layers: &[Node], rules: &HashMap<String, Vec<String>>) -> Vec<Vec<core::dependencies::EdgeInfo>> {
+// Warns about a namespace dependency that has no matching ProjectReference
+fn display_reference_leak(nodes: &[Node], dependencies: &NodeDependencies, leak: &ReferenceLeak) {
+    let location = dependencies[leak.from].iter()
+        .find(|edge| edge.to == leak.to)
+        .and_then(|edge| edge.locations.first())
+        .map(|location| format!("{}:{}: ", location.file, location.line))
+        .unwrap_or_default();
+    let reason = if leak.transitive { "only references it transitively" } else { "does not reference it" };
+    eprintln!(
+        "Warning: {}'{}' ({}) uses '{}' from {}, but {} {}",
+        location, nodes[leak.from].name, nodes[leak.from_project].name, nodes[leak.to].name,
+        nodes[leak.to_project].name, nodes[leak.from_project].name, reason
+    );
+}
+
+// Nodes behind the displayed collapsed nodes and their dependencies, which the HTML output
+// expands collapsed namespaces into
+fn drill_down_graph(nodes: &[Node], dependencies: &NodeDependencies, shown_nodes: &[Node], segments: usize) -> (Vec<Node>, NodeDependencies) {
+    let shown: HashSet<&str> = shown_nodes.iter().map(|node| node.id.as_str()).collect();
+    let keep: Vec<bool> = nodes.iter()
+        .map(|node| match node.node_type.as_str() {
+            NAMESPACE_NODE_TYPE => shown.contains(truncate_namespace(&node.id, segments).as_str()),
+            _ => shown.contains(node.id.as_str()),
+        })
+        .collect();
+    subgraph(nodes, dependencies, &keep)
+}
+
+fn get_layer_dependencies(layers: &[Node], rules: &HashMap<String, Vec<String>>) -> NodeDependencies {
     // Precompute layer indices for quick lookup
     let layer_indices: HashMap<&String, usize> = layers.iter().enumerate()
         .map(|(index, layer)| (&layer.id, index))
@@ -165,7 +566,7 @@ fn get_layer_dependencies(layers: &[Node], rules: &HashMap<String, Vec<String>>)
             let to_layer_index = *layer_indices.get(layer_rule).unwrap();
             let to_layer = &layers[to_layer_index];
             let label = format!("{} -> {}", layer.name, to_layer.name);
-            core::dependencies::EdgeInfo { to: to_layer_index, allowed: true, label }
+            EdgeInfo { to: to_layer_index, allowed: true, label, weight: 1, locations: Vec::new(), types: Vec::new(), merged: Vec::new() }
         }).collect()
     }).collect()
 }
@@ -182,6 +583,7 @@ fn get_layers(config: &config::types::Config) -> Vec<Node> {
                 Some(color) => color.clone(),
                 None => "gray".to_string(), // Or handle the None case as needed
             },
+            group: None,
         });
     }
     layers
@@ -189,47 +591,319 @@ fn get_layers(config: &config::types::Config) -> Vec<Node> {
 
 fn generate_output(
     cli: &Cli,
-    nodes: &[Node],
-    dependencies: &NodeDependencies,
+    root_path: &Path,
+    result: &AnalysisResult,
     layers: &[Node],
     layer_dependencies: &NodeDependencies,
     config: &Config
 ) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(Command::Impact { node, changed_files, ids }) = &cli.command {
+        return run_impact(node.as_deref(), *changed_files, *ids, root_path, result, config);
+    }
+    let (nodes, dependencies) = (result.nodes.as_slice(), &result.dependencies);
+
+    // Keep stdout parseable when a JSON document is written there: everything else goes to stderr
+    let mut out: Box<dyn io::Write> = if matches!(cli.output.as_deref(), Some("json" | "sarif")) && cli.output_file.is_none() {
+        Box::new(io::stderr())
+    } else {
+        Box::new(io::stdout())
+    };
+
+    if cli.reference_report || cli.fix_suggestions {
+        display_reference_findings(&mut out, cli, root_path, result, config)?;
+    }
+
+    let filter = graph_filter(cli)?;
+
     // display the number of elements that nodes and dependencies have
-    println!("Nodes: {}", nodes.len());
-    println!("Dependencies: {}", dependencies.len());
-    println!("Layers: {}", layers.len());
-    println!("Layer Dependencies: {}", layer_dependencies.len());
+    writeln!(
+        out,
+        "Nodes: {}\nDependencies: {}\nLayers: {}\nLayer Dependencies: {}",
+        nodes.len(), dependencies.len(), layers.len(), layer_dependencies.len()
+    )?;
+
+    // Collapsing, filters and grouping only apply to the displayed graph (--list, Mermaid,
+    // Graphviz and HTML); checks, metrics, the JSON and SARIF reports use the whole graph
+    let collapsed = cli.collapse_namespaces
+        .map(|segments| NamespaceDependencyManager::collapse_namespaces(nodes, dependencies, segments, config));
+    let (shown_nodes, shown_dependencies) = match &collapsed {
+        Some((collapsed_nodes, collapsed_dependencies)) => (collapsed_nodes.as_slice(), collapsed_dependencies),
+        None => (nodes, dependencies),
+    };
+    if let Some(focus) = &filter.focus {
+        if focused_nodes(shown_nodes, focus).is_empty() {
+            eprintln!("Warning: --focus pattern '{}' matches no node", focus);
+        }
+    }
+    let filtered = (!filter.is_empty()).then(|| filter_graph(shown_nodes, shown_dependencies, &filter));
+    let (shown_nodes, shown_dependencies) = match &filtered {
+        Some((filtered_nodes, filtered_dependencies)) => {
+            eprintln!("Showing {} of {} nodes after filtering", filtered_nodes.len(), shown_nodes.len());
+            (filtered_nodes.as_slice(), filtered_dependencies)
+        }
+        None => (shown_nodes, shown_dependencies),
+    };
+    let group_by = cli.group_by.map(|grouping| match grouping {
+        Grouping::Layer => GroupBy::Layer,
+    });
+    let grouped = group_by.map(|group_by| match group_by {
+        GroupBy::Layer => group_by_layer(shown_nodes, shown_dependencies),
+    });
+    let (shown_nodes, shown_dependencies) = match &grouped {
+        Some((grouped_nodes, grouped_dependencies)) => (grouped_nodes.as_slice(), grouped_dependencies),
+        None => (shown_nodes, shown_dependencies),
+    };
+
     if cli.list {
-        display_graph_information(&nodes, &dependencies);
-        display_graph_information(&layers, &layer_dependencies)
+        display_graph_information(&mut out, shown_nodes, shown_dependencies)?;
+        display_graph_information(&mut out, layers, layer_dependencies)?;
+    }
+
+    let metrics = if cli.metrics || cli.metrics_file.is_some() {
+        let mut scanned = None;
+        let type_counts = match config.csharp {
+            Some(_) => NamespaceDependencyManager::type_counts(namespace_scan(result, &mut scanned, root_path, config)?),
+            None => HashMap::new(),
+        };
+        let node_type_counts: Vec<Option<TypeCounts>> = nodes.iter()
+            .map(|node| match node.node_type.as_str() {
+                "project" | "namespace" => type_counts.get(&node.id).copied(),
+                _ => None,
+            })
+            .collect();
+        Some(compute_metrics(nodes, dependencies, &node_type_counts))
+    } else {
+        None
+    };
+    if let Some(metrics) = &metrics {
+        if cli.metrics {
+            display_metrics(&mut out, metrics)?;
+        }
+        if let Some(path) = &cli.metrics_file {
+            write_metrics_file(metrics, path)?;
+            writeln!(out, "Metrics written to '{}'", path)?;
+        }
     }
 
     if let Some(format) = &cli.output {
         if let Some(html_path) = &cli.output_html {
-            generate_html_output(&nodes, &dependencies, &layers, &layer_dependencies, html_path, format, &config.global.toggles)?;
+            let chart = metrics.as_deref().filter(|_| cli.metrics);
+            let segments = cli.collapse_namespaces.filter(|_| cli.group_by.is_none());
+            let drill_down_graph = segments.map(|segments| drill_down_graph(nodes, dependencies, shown_nodes, segments));
+            let drill_down = segments.zip(drill_down_graph.as_ref()).map(|(segments, (drill_nodes, drill_dependencies))| DrillDown {
+                nodes: drill_nodes,
+                node_dependencies: drill_dependencies,
+                prefixes: NamespaceDependencyManager::namespace_prefixes(drill_nodes, segments, config),
+                segments,
+                rules: &config.global.rules,
+            });
+            let options = HtmlOptions { path: html_path, format, toggles: &config.global.toggles, metrics: chart, drill_down: drill_down.as_ref() };
+            writeln!(out, "Generating HTML output at '{}' using format '{}'", html_path, format)?;
+            generate_html_output(shown_nodes, shown_dependencies, layers, layer_dependencies, &options)?;
         } else {
             match format.as_str() {
-                "mermaid" => generate_mermaid_diagram(&nodes, &dependencies),
-                "graphviz" => generate_graphviz_diagram(&nodes, &dependencies, &layers, &layer_dependencies, &config.global.toggles),
+                "mermaid" => generate_mermaid_diagram(shown_nodes, shown_dependencies),
+                "graphviz" => generate_graphviz_diagram(shown_nodes, shown_dependencies, layers, layer_dependencies, &config.global.toggles),
                 "d3" => eprintln!("D3 output is only available for HTML output."),
-                _ => eprintln!("Invalid format. Use 'mermaid' or 'graphviz'."),
+                // Reports describe the whole graph, whatever the display options
+                "json" => {
+                    let cycles = detect_cycles(dependencies);
+                    let mut report = build_json_report(&cli.analysis, root_path, nodes, dependencies, layers, layer_dependencies, &cycles);
+                    let solution_path = cli.solution.as_ref().map(|solution| Path::new(solution).canonicalize()).transpose()?;
+                    report.options = report_options(cli, root_path, solution_path.as_deref());
+                    write_json_output(&report, cli.output_file.as_deref())?;
+                    if let Some(path) = &cli.output_file {
+                        writeln!(out, "Output written to '{}'", path)?;
+                    }
+                }
+                "sarif" => {
+                    let source_root = repository_root(root_path).unwrap_or_else(|| root_path.to_path_buf());
+                    let sarif = build_sarif_report(nodes, dependencies, &config.global.rules, &source_root);
+                    write_json_output(&sarif, cli.output_file.as_deref())?;
+                    if let Some(path) = &cli.output_file {
+                        writeln!(out, "Output written to '{}'", path)?;
+                    }
+                }
+                _ => eprintln!("Invalid format. Use 'mermaid', 'graphviz', 'json' or 'sarif'."),
             }
         }
     }
 
     if cli.detect_cycles {
-        let has_cycle = detect_cycles(&nodes, &dependencies);
-        if has_cycle {
+        let cycles = detect_cycles(dependencies);
+        display_cycles(&mut out, nodes, &cycles)?;
+        // With --check, cycles are reported through its exit code once the baseline is applied
+        if !cycles.is_empty() && !cli.check {
             eprintln!("Cycle detected in dependencies.");
             std::process::exit(1);
         }
     }
 
+    if cli.build_order {
+        display_build_order(&mut out, nodes, &build_order(dependencies))?;
+    }
+
+    let baseline_path = match &cli.baseline {
+        Some(path) => PathBuf::from(path),
+        None => root_path.join(BASELINE_FILE_NAME),
+    };
+
+    if cli.generate_baseline {
+        let result = check_dependencies(nodes, dependencies, config.global.stable_dependencies.as_ref());
+        let baseline = Baseline::from_check_result(&cli.analysis, nodes, &result, root_path);
+        baseline.save(&baseline_path)?;
+        writeln!(out, "Baseline with {} violations written to {:?}", baseline.violations.len(), baseline_path)?;
+    }
+
+    if cli.check {
+        let mut result = check_dependencies(nodes, dependencies, config.global.stable_dependencies.as_ref());
+        if baseline_path.exists() {
+            let baseline = Baseline::load(&baseline_path)?;
+            if baseline.analysis != cli.analysis {
+                eprintln!("Warning: baseline {:?} was generated for '{}', not '{}'.", baseline_path, baseline.analysis, cli.analysis);
+            }
+            writeln!(out, "Using baseline {:?}", baseline_path)?;
+            baseline.apply(nodes, &mut result, root_path);
+        } else if cli.baseline.is_some() {
+            return Err(Box::from(format!("Baseline file not found: {:?}", baseline_path)));
+        }
+        display_check_summary(&mut out, nodes, &result)?;
+        if !result.passed() {
+            std::process::exit(result.exit_code());
+        }
+    }
+
+    Ok(())
+}
+
+// Reference report and fix suggestions, built on the combined project and namespace graph. The
+// `csharp:all` graph is reused; other analyses build it from their scan, if they have one.
+fn display_reference_findings(out: &mut dyn io::Write, cli: &Cli, root_path: &Path, result: &AnalysisResult, config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    let mut scanned = None;
+    let combined;
+    let (nodes, dependencies, scan) = match (cli.analysis.as_str(), &result.scan) {
+        ("csharp:all", Some(scan)) => (&result.nodes, &result.dependencies, scan),
+        _ => {
+            let scan = namespace_scan(result, &mut scanned, root_path, config)?;
+            let solution_path = cli.solution.as_ref().map(|solution| Path::new(solution).canonicalize()).transpose()?;
+            let projects = collect_project_nodes(root_path, solution_path.as_deref(), config)?;
+            let nodes = CombinedDependencyManager::add_namespace_nodes(projects, scan, config)?;
+            let dependencies = CombinedDependencyManager::find_dependencies(scan, &nodes, config)?;
+            combined = (nodes, dependencies);
+            (&combined.0, &combined.1, scan)
+        }
+    };
+    let report = CombinedDependencyManager::reference_report(scan, nodes, dependencies);
+    if cli.reference_report {
+        display_reference_report(out, nodes, &report)?;
+    }
+    if cli.fix_suggestions {
+        display_fix_suggestions(out, nodes, &report.unused)?;
+    }
+    Ok(())
+}
+
+fn graph_filter(cli: &Cli) -> Result<GraphFilter, Box<dyn std::error::Error>> {
+    let focus = match &cli.focus {
+        Some(pattern) => Some(Regex::new(pattern).map_err(|e| format!("Invalid --focus pattern '{}': {}", pattern, e))?),
+        None => None,
+    };
+    Ok(GraphFilter {
+        focus,
+        depth: cli.depth,
+        direction: match cli.direction {
+            Direction::Up => FocusDirection::Up,
+            Direction::Down => FocusDirection::Down,
+            Direction::Both => FocusDirection::Both,
+        },
+        only_layers: cli.only_layers.clone(),
+        exclude_layers: cli.exclude_layers.clone(),
+        only_violations: cli.only_violations,
+        hide_unknown: cli.hide_unknown,
+    })
+}
+
+// Lists the nodes affected by the node given on the command line and by the files read from stdin
+fn run_impact(
+    node: Option<&str>,
+    changed_files: bool,
+    ids: bool,
+    root_path: &Path,
+    result: &AnalysisResult,
+    config: &Config
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (nodes, dependencies) = (result.nodes.as_slice(), &result.dependencies);
+    let mut changed = Vec::new();
+    if let Some(query) = node {
+        let matches = find_nodes(nodes, query);
+        if matches.is_empty() {
+            return Err(Box::from(format!("No node matches '{}'", query)));
+        }
+        changed.extend(matches);
+    }
+
+    if changed_files {
+        // `git diff --name-only` prints paths relative to the repository root, whatever the
+        // current directory; outside of a repository, paths are relative to the current directory
+        let base_dir = match repository_root(root_path) {
+            Some(toplevel) => toplevel,
+            None => env::current_dir()?,
+        };
+        let files: Vec<PathBuf> = io::stdin().lines()
+            .collect::<Result<Vec<_>, _>>()?
+            .iter()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty())
+            .map(|line| base_dir.join(line))
+            .collect();
+        let mut scanned = None;
+        let owners = match config.csharp {
+            Some(_) => NamespaceDependencyManager::file_owners(namespace_scan(result, &mut scanned, root_path, config)?, &files)?,
+            None => vec![Vec::new(); files.len()],
+        };
+        let node_index: HashMap<&str, usize> = nodes.iter().enumerate()
+            .map(|(index, node)| (node.id.as_str(), index))
+            .collect();
+        for (file, ids) in files.iter().zip(owners) {
+            let indices: Vec<usize> = ids.iter().filter_map(|id| node_index.get(id.as_str()).copied()).collect();
+            if indices.is_empty() {
+                eprintln!("Warning: {} does not belong to any analyzed node", file.display());
+            }
+            changed.extend(indices);
+        }
+    }
+
+    let impacted = impacted_nodes(dependencies, &changed);
+    let mut out = io::stdout().lock();
+    if ids {
+        for node in &impacted {
+            writeln!(out, "{}", nodes[node.index].id)?;
+        }
+    } else {
+        display_impact(&mut out, nodes, &impacted)?;
+    }
     Ok(())
 }
 
-fn generate_default_config(folder: &PathBuf, languages: &str) -> Result<(), Box<dyn std::error::Error>> {
+// Nodes matching an id, a path to a project file, or a name (case-insensitive, `.csproj` optional)
+fn find_nodes(nodes: &[Node], query: &str) -> Vec<usize> {
+    let path = Path::new(query).canonicalize().ok().map(|path| path.to_string_lossy().to_string());
+    let by_id: Vec<usize> = (0..nodes.len())
+        .filter(|&index| nodes[index].id == query || path.as_ref() == Some(&nodes[index].id))
+        .collect();
+    if !by_id.is_empty() {
+        return by_id;
+    }
+    (0..nodes.len())
+        .filter(|&index| {
+            let name = &nodes[index].name;
+            name.eq_ignore_ascii_case(query)
+                || (nodes[index].node_type == PROJECT_NODE_TYPE && name.strip_suffix(".csproj").is_some_and(|stem| stem.eq_ignore_ascii_case(query)))
+        })
+        .collect()
+}
+
+fn generate_default_config(folder: &Path, languages: &str) -> Result<(), Box<dyn std::error::Error>> {
     let mut config = Config::default();
     let langs: Vec<&str> = languages.split(',').collect();
 
@@ -250,3 +924,88 @@ fn generate_default_config(folder: &PathBuf, languages: &str) -> Result<(), Box<
     to_writer_pretty(file, &config_map)?;
     Ok(())
 }
+
+fn load_reports(old: &str, new: &str) -> Result<(JsonReport, JsonReport), Box<dyn std::error::Error>> {
+    let load = |path: &str| load_report(Path::new(path)).map_err(|e| format!("Cannot read the JSON report '{}': {}", path, e));
+    Ok((load(old)?, load(new)?))
+}
+
+// Reports of the analyzed folder at `revision` and as it is now. The revision is checked out in
+// a temporary git worktree, analyzed with its own configuration, and removed afterwards.
+fn revision_reports(
+    cli: &Cli,
+    revision: &str,
+    root_path: &Path,
+    solution_path: Option<&Path>,
+    config: &Config
+) -> Result<(JsonReport, JsonReport), Box<dyn std::error::Error>> {
+    let new_report = analysis_report(cli, root_path, solution_path, config)?;
+
+    let toplevel = PathBuf::from(git(root_path, &["rev-parse", "--show-toplevel"])?.trim()).canonicalize()?;
+    let worktree = env::temp_dir().join(format!("depscop-diff-{}", std::process::id()));
+    let worktree_arg = worktree.to_string_lossy().to_string();
+    git(&toplevel, &["worktree", "add", "--detach", "--quiet", &worktree_arg, revision])?;
+    let old_report = (|| {
+        let worktree = worktree.canonicalize()?;
+        let old_root = worktree.join(root_path.strip_prefix(&toplevel)?);
+        let old_solution = solution_path
+            .map(|solution| solution.strip_prefix(&toplevel).map(|relative| worktree.join(relative)))
+            .transpose()?;
+        let old_config = cli_config(cli, &old_root);
+        analysis_report(cli, &old_root, old_solution.as_deref(), &old_config)
+    })();
+    let removed = git(&toplevel, &["worktree", "remove", "--force", &worktree_arg]);
+
+    let old_report = old_report?;
+    removed?;
+    Ok((old_report, new_report))
+}
+
+// JSON report of the whole graph of an analysis, as written by `--output json`
+fn analysis_report(cli: &Cli, root_path: &Path, solution_path: Option<&Path>, config: &Config) -> Result<JsonReport, Box<dyn std::error::Error>> {
+    let result = analyze(&cli.analysis, root_path, solution_path, config)?;
+    let layers = get_layers(config);
+    let layer_dependencies = get_layer_dependencies(&layers, &config.global.rules);
+    let cycles = detect_cycles(&result.dependencies);
+    let mut report = build_json_report(&cli.analysis, root_path, &result.nodes, &result.dependencies, &layers, &layer_dependencies, &cycles);
+    report.options = report_options(cli, root_path, solution_path);
+    Ok(report)
+}
+
+// Options of the command line recorded in the JSON report
+fn report_options(cli: &Cli, root_path: &Path, solution_path: Option<&Path>) -> JsonReportOptions {
+    JsonReportOptions {
+        solution: solution_path.map(|solution| match solution.strip_prefix(root_path) {
+            Ok(relative) => relative.to_slash_lossy().into_owned(),
+            Err(_) => solution.to_string_lossy().into_owned(),
+        }),
+        properties: cli.properties.iter().cloned().collect(),
+        type_references: cli.type_references,
+    }
+}
+
+// Runs git in `directory` and returns its standard output
+// Top-level folder of the git repository containing `path`, if any
+fn repository_root(path: &Path) -> Option<PathBuf> {
+    let toplevel = git(path, &["rev-parse", "--show-toplevel"]).ok()?;
+    let toplevel = PathBuf::from(toplevel.trim());
+    Some(toplevel.canonicalize().unwrap_or(toplevel))
+}
+
+fn git(directory: &Path, args: &[&str]) -> Result<String, Box<dyn std::error::Error>> {
+    let output = std::process::Command::new("git").arg("-C").arg(directory).args(args).output()?;
+    if !output.status.success() {
+        return Err(Box::from(format!("git {} failed: {}", args.join(" "), String::from_utf8_lossy(&output.stderr).trim())));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+fn display_diff(old_report: &JsonReport, new_report: &JsonReport, format: DiffFormat) -> Result<(), Box<dyn std::error::Error>> {
+    let diff = diff_reports(old_report, new_report)?;
+    match format {
+        DiffFormat::Markdown => display_diff_markdown(&diff),
+        DiffFormat::Mermaid => generate_diff_mermaid(&diff),
+        DiffFormat::Graphviz => generate_diff_graphviz(&diff),
+    }
+    Ok(())
+}
@@ -0,0 +1,373 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs::File;
+use std::io::{BufReader, Error};
+use std::path::Path;
+use path_slash::PathExt;
+
+use crate::output::json::{JsonCycle, JsonEdge, JsonLink, JsonNode, JsonReport};
+use crate::output::static_output::{dot_string, mermaid_label};
+
+/// Changes between two JSON reports. Nodes are matched by id, made relative to the analyzed
+/// folder of each report so that snapshots of different checkouts can be compared.
+#[derive(Debug, Clone, Default)]
+pub struct ReportDiff {
+    pub analysis: String,
+    /// Every node of both reports by id, as found in the new report when it has the node.
+    pub nodes: BTreeMap<String, JsonNode>,
+    pub added_nodes: Vec<String>,
+    pub removed_nodes: Vec<String>,
+    pub added_edges: Vec<JsonEdge>,
+    pub removed_edges: Vec<JsonEdge>,
+    /// Disallowed edges of the new report that were missing or allowed in the old one.
+    pub new_violations: Vec<JsonEdge>,
+    /// Cycles of the new report whose members were not all part of a single cycle in the old one.
+    pub new_cycles: Vec<JsonCycle>,
+}
+
+impl ReportDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added_nodes.is_empty()
+            && self.removed_nodes.is_empty()
+            && self.added_edges.is_empty()
+            && self.removed_edges.is_empty()
+            && self.new_violations.is_empty()
+            && self.new_cycles.is_empty()
+    }
+}
+
+pub fn load_report(path: &Path) -> Result<JsonReport, Error> {
+    let file = File::open(path)?;
+    serde_json::from_reader(BufReader::new(file)).map_err(Error::other)
+}
+
+/// Compares the nodes, edges and violations of two reports. Fails when the reports come from
+/// different analyses or options, which would show every difference between them as a change.
+pub fn diff_reports(old: &JsonReport, new: &JsonReport) -> Result<ReportDiff, Box<dyn std::error::Error>> {
+    if old.analysis != new.analysis {
+        return Err(Box::from(format!("Cannot compare a '{}' report with a '{}' report", old.analysis, new.analysis)));
+    }
+    if old.options != new.options {
+        return Err(Box::from(format!(
+            "Cannot compare reports generated with different options: {} and {}",
+            serde_json::to_string(&old.options)?, serde_json::to_string(&new.options)?
+        )));
+    }
+    let (old, new) = (relative_ids(old), relative_ids(new));
+
+    let old_nodes: BTreeSet<&str> = old.nodes.iter().map(|node| node.id.as_str()).collect();
+    let new_nodes: BTreeSet<&str> = new.nodes.iter().map(|node| node.id.as_str()).collect();
+    let old_edges: HashMap<(&str, &str), &JsonEdge> = old.edges.iter()
+        .map(|edge| ((edge.from.as_str(), edge.to.as_str()), edge))
+        .collect();
+    let new_edges: HashMap<(&str, &str), &JsonEdge> = new.edges.iter()
+        .map(|edge| ((edge.from.as_str(), edge.to.as_str()), edge))
+        .collect();
+    let old_cycles: Vec<BTreeSet<&str>> = old.cycles.iter()
+        .map(|cycle| cycle.members.iter().map(String::as_str).collect())
+        .collect();
+
+    let mut nodes: BTreeMap<String, JsonNode> = BTreeMap::new();
+    for node in old.nodes.iter().chain(&new.nodes) {
+        nodes.insert(node.id.clone(), node.clone());
+    }
+
+    Ok(ReportDiff {
+        analysis: new.analysis.clone(),
+        added_nodes: new.nodes.iter()
+            .filter(|node| !old_nodes.contains(node.id.as_str()))
+            .map(|node| node.id.clone())
+            .collect(),
+        removed_nodes: old.nodes.iter()
+            .filter(|node| !new_nodes.contains(node.id.as_str()))
+            .map(|node| node.id.clone())
+            .collect(),
+        added_edges: new.edges.iter()
+            .filter(|edge| !old_edges.contains_key(&(edge.from.as_str(), edge.to.as_str())))
+            .cloned()
+            .collect(),
+        removed_edges: old.edges.iter()
+            .filter(|edge| !new_edges.contains_key(&(edge.from.as_str(), edge.to.as_str())))
+            .cloned()
+            .collect(),
+        new_violations: new.edges.iter()
+            .filter(|edge| !edge.allowed)
+            .filter(|edge| old_edges.get(&(edge.from.as_str(), edge.to.as_str())).is_none_or(|old_edge| old_edge.allowed))
+            .cloned()
+            .collect(),
+        new_cycles: new.cycles.iter()
+            .filter(|cycle| {
+                let members: BTreeSet<&str> = cycle.members.iter().map(String::as_str).collect();
+                !old_cycles.iter().any(|old_members| members.is_subset(old_members))
+            })
+            .cloned()
+            .collect(),
+        nodes,
+    })
+}
+
+// Copy of the report with the ids under its root made relative, like `Node::stable_id`
+fn relative_ids(report: &JsonReport) -> JsonReport {
+    let relative = |id: &String| match &report.root {
+        Some(root) => match Path::new(id).strip_prefix(root) {
+            Ok(relative) => relative.to_slash_lossy().into_owned(),
+            Err(_) => id.clone(),
+        },
+        None => id.clone(),
+    };
+    let mut report = report.clone();
+    for node in &mut report.nodes {
+        node.id = relative(&node.id);
+    }
+    for edge in &mut report.edges {
+        edge.from = relative(&edge.from);
+        edge.to = relative(&edge.to);
+    }
+    for cycle in &mut report.cycles {
+        cycle.members = cycle.members.iter().map(relative).collect();
+        cycle.cycle = cycle.cycle.iter().map(relative).collect();
+        cycle.closing_edges = cycle.closing_edges.iter()
+            .map(|link| JsonLink { from: relative(&link.from), to: relative(&link.to) })
+            .collect();
+    }
+    report
+}
+
+pub fn display_diff_markdown(diff: &ReportDiff) {
+    let name = |id: &String| diff.nodes.get(id).map_or(id.as_str(), |node| node.name.as_str()).to_string();
+    let edge = |edge: &JsonEdge| format!("`{}` -> `{}`", name(&edge.from), name(&edge.to));
+    let node = |id: &String| {
+        let layer = diff.nodes.get(id).map_or("unknown", |node| node.layer.as_str());
+        format!("`{}` ({})", name(id), layer)
+    };
+
+    println!("## Dependency changes ({})", diff.analysis);
+    println!();
+    if diff.is_empty() {
+        println!("No dependency changes.");
+        return;
+    }
+    println!(
+        "Nodes: +{} -{}, edges: +{} -{}, new violations: {}",
+        diff.added_nodes.len(), diff.removed_nodes.len(), diff.added_edges.len(), diff.removed_edges.len(),
+        diff.new_violations.len() + diff.new_cycles.len()
+    );
+
+    let mut violations: Vec<String> = diff.new_violations.iter()
+        .map(|violation| {
+            let layer = |id: &String| diff.nodes.get(id).map_or("unknown", |node| node.layer.as_str()).to_string();
+            format!("{} ({} -> {})", edge(violation), layer(&violation.from), layer(&violation.to))
+        })
+        .collect();
+    violations.extend(diff.new_cycles.iter().map(|cycle| {
+        let path: Vec<String> = cycle.cycle.iter().map(|id| format!("`{}`", name(id))).collect();
+        format!("Cycle: {}", path.join(" -> "))
+    }));
+    markdown_section("New violations", &violations);
+    markdown_section("Added nodes", &diff.added_nodes.iter().map(node).collect::<Vec<_>>());
+    markdown_section("Removed nodes", &diff.removed_nodes.iter().map(node).collect::<Vec<_>>());
+    markdown_section("Added dependencies", &diff.added_edges.iter().map(edge).collect::<Vec<_>>());
+    markdown_section("Removed dependencies", &diff.removed_edges.iter().map(edge).collect::<Vec<_>>());
+}
+
+fn markdown_section(title: &str, items: &[String]) {
+    if items.is_empty() {
+        return;
+    }
+    println!();
+    println!("### {}", title);
+    println!();
+    for item in items {
+        println!("- {}", item);
+    }
+}
+
+// Change drawn in the diff diagrams
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum EdgeChange {
+    Added,
+    Removed,
+    // Kept edge that a rule change made disallowed
+    Disallowed,
+}
+
+// Edge of the diff diagrams, between node indices
+struct DiagramEdge<'a> {
+    from: usize,
+    to: usize,
+    edge: &'a JsonEdge,
+    change: EdgeChange,
+}
+
+// Changed edges, with the nodes they connect plus the added and removed nodes, in order of
+// appearance
+fn diagram_graph(diff: &ReportDiff) -> (Vec<&String>, Vec<DiagramEdge<'_>>) {
+    let disallowed = diff.new_violations.iter()
+        .filter(|violation| !diff.added_edges.iter().any(|edge| edge.from == violation.from && edge.to == violation.to));
+    let changes: Vec<(&JsonEdge, EdgeChange)> = diff.added_edges.iter().map(|edge| (edge, EdgeChange::Added))
+        .chain(diff.removed_edges.iter().map(|edge| (edge, EdgeChange::Removed)))
+        .chain(disallowed.map(|edge| (edge, EdgeChange::Disallowed)))
+        .collect();
+
+    let mut ids: Vec<&String> = Vec::new();
+    let mut edges = Vec::new();
+    for id in diff.added_nodes.iter().chain(&diff.removed_nodes) {
+        position_or_push(&mut ids, id);
+    }
+    for (edge, change) in changes {
+        let from = position_or_push(&mut ids, &edge.from);
+        let to = position_or_push(&mut ids, &edge.to);
+        edges.push(DiagramEdge { from, to, edge, change });
+    }
+    (ids, edges)
+}
+
+fn position_or_push<'a>(ids: &mut Vec<&'a String>, id: &'a String) -> usize {
+    match ids.iter().position(|existing| *existing == id) {
+        Some(index) => index,
+        None => {
+            ids.push(id);
+            ids.len() - 1
+        }
+    }
+}
+
+/// Diagram of the changed dependencies: added edges in green, removed edges in grey and kept
+/// edges that became disallowed in red. Added nodes have a green border, removed nodes are grey.
+pub fn generate_diff_mermaid(diff: &ReportDiff) {
+    let (ids, edges) = diagram_graph(diff);
+    println!("```mermaid");
+    println!("graph TD;");
+    for (index, id) in ids.iter().enumerate() {
+        let name = diff.nodes.get(*id).map_or(id.as_str(), |node| node.name.as_str());
+        println!("    P{}[\"{}\"]", index + 1, mermaid_label(name));
+    }
+    for DiagramEdge { from, to, edge, change } in &edges {
+        let arrow = if *change == EdgeChange::Removed { "-.->" } else { "-->" };
+        let label = if edge.allowed || *change == EdgeChange::Removed { "" } else { "|not allowed|" };
+        println!("    P{} {}{} P{}", from + 1, arrow, label, to + 1);
+    }
+    for (index, edge) in edges.iter().enumerate() {
+        let color = match edge.change {
+            EdgeChange::Added => "green",
+            EdgeChange::Removed => "grey",
+            EdgeChange::Disallowed => "red",
+        };
+        println!("    linkStyle {} stroke:{},stroke-width:2px;", index, color);
+    }
+    for (index, id) in ids.iter().enumerate() {
+        if diff.added_nodes.contains(id) {
+            println!("    style P{} stroke:green,stroke-width:3px", index + 1);
+        } else if diff.removed_nodes.contains(id) {
+            println!("    style P{} stroke:grey,stroke-dasharray:5 5,color:grey", index + 1);
+        }
+    }
+    println!("```");
+}
+
+/// Graphviz version of `generate_diff_mermaid`, with the layer colors of the nodes.
+pub fn generate_diff_graphviz(diff: &ReportDiff) {
+    let (ids, edges) = diagram_graph(diff);
+    println!("digraph G {{");
+    println!("    node [color=grey, style=filled];");
+    println!("    node [fontname=\"Verdana\", size=\"30,30\"];");
+    for (index, id) in ids.iter().enumerate() {
+        let (name, color) = diff.nodes.get(*id).map_or((id.as_str(), "gray"), |node| (node.name.as_str(), node.color.as_str()));
+        let change = if diff.added_nodes.contains(id) {
+            ", color=\"green\", penwidth=3"
+        } else if diff.removed_nodes.contains(id) {
+            ", style=\"filled,dashed\", fontcolor=\"grey\""
+        } else {
+            ""
+        };
+        println!("    P{} [label=\"{}\", style=filled, fillcolor=\"{}\"{}]", index + 1, dot_string(name), dot_string(color), change);
+    }
+    for DiagramEdge { from, to, edge, change } in &edges {
+        let attributes = match change {
+            EdgeChange::Added if !edge.allowed => "color=\"green\" penwidth=2 label=\"not allowed\" fontcolor=\"red\"",
+            EdgeChange::Added => "color=\"green\" penwidth=2",
+            EdgeChange::Removed => "color=\"grey\" style=dashed",
+            EdgeChange::Disallowed => "color=\"red\" style=dotted penwidth=2 label=\"not allowed\" fontcolor=\"red\"",
+        };
+        println!("    P{} -> P{} [{}]", from + 1, to + 1, attributes);
+    }
+    println!("}}");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn report(root: &str, nodes: &[&str], edges: &[(&str, &str, bool)], cycles: &[&[&str]]) -> JsonReport {
+        let node = |id: &&str| json!({ "id": format!("{}/{}", root, id), "name": id, "layer": "layer", "node_type": "project", "color": "" });
+        let edge = |(from, to, allowed): &(&str, &str, bool)| json!({
+            "from": format!("{}/{}", root, from), "to": format!("{}/{}", root, to), "allowed": allowed, "label": "",
+        });
+        let cycle = |members: &&[&str]| {
+            let ids: Vec<String> = members.iter().map(|id| format!("{}/{}", root, id)).collect();
+            json!({ "members": ids, "cycle": ids, "closing_edges": [] })
+        };
+        serde_json::from_value(json!({
+            "schema_version": 1,
+            "generator": "depscop",
+            "generated_at": "",
+            "analysis": "csharp:projects",
+            "root": root,
+            "summary": { "nodes": 0, "edges": 0, "allowed_edges": 0, "disallowed_edges": 0, "unknown_nodes": 0, "layers": 0, "cycles": 0 },
+            "nodes": nodes.iter().map(node).collect::<Vec<_>>(),
+            "edges": edges.iter().map(edge).collect::<Vec<_>>(),
+            "layers": [],
+            "layer_rules": [],
+            "cycles": cycles.iter().map(cycle).collect::<Vec<_>>(),
+        })).unwrap()
+    }
+
+    fn links(edges: &[JsonEdge]) -> Vec<(&str, &str)> {
+        edges.iter().map(|edge| (edge.from.as_str(), edge.to.as_str())).collect()
+    }
+
+    #[test]
+    fn ids_are_relative_to_the_report_root() {
+        let relative = relative_ids(&report("/old", &["A", "B"], &[("A", "B", true)], &[&["A", "B"]]));
+        assert_eq!(relative.nodes[0].id, "A");
+        assert_eq!(links(&relative.edges), [("A", "B")]);
+        assert_eq!(relative.cycles[0].members, ["A", "B"]);
+
+        let mut outside = report("/old", &["A"], &[], &[]);
+        outside.nodes[0].id = "System.Linq".to_string();
+        outside.root = None;
+        assert_eq!(relative_ids(&outside).nodes[0].id, "System.Linq");
+    }
+
+    #[test]
+    fn changes_between_checkouts_are_reported() {
+        let old = report("/old", &["A", "B", "C"], &[("A", "B", true), ("B", "C", false), ("C", "A", true)], &[]);
+        let new = report("/new", &["A", "B", "D"], &[("A", "B", false), ("B", "D", true), ("D", "B", false)], &[&["B", "D"]]);
+        let diff = diff_reports(&old, &new).unwrap();
+        assert_eq!(diff.added_nodes, ["D"]);
+        assert_eq!(diff.removed_nodes, ["C"]);
+        assert_eq!(links(&diff.added_edges), [("B", "D"), ("D", "B")]);
+        assert_eq!(links(&diff.removed_edges), [("B", "C"), ("C", "A")]);
+        assert_eq!(links(&diff.new_violations), [("A", "B"), ("D", "B")]);
+        assert_eq!(diff.new_cycles.len(), 1);
+        assert_eq!(diff.nodes.keys().collect::<Vec<_>>(), ["A", "B", "C", "D"]);
+    }
+
+    #[test]
+    fn identical_reports_have_no_changes() {
+        let old = report("/old", &["A", "B"], &[("A", "B", false)], &[&["A", "B"]]);
+        let new = report("/new", &["A", "B"], &[("A", "B", false)], &[&["A", "B"]]);
+        assert!(diff_reports(&old, &new).unwrap().is_empty());
+    }
+
+    #[test]
+    fn reports_of_different_analyses_or_options_are_rejected() {
+        let old = report("/old", &["A"], &[], &[]);
+        let mut new = report("/new", &["A"], &[], &[]);
+        new.analysis = "csharp:namespaces".to_string();
+        assert!(diff_reports(&old, &new).is_err());
+        new.analysis = old.analysis.clone();
+        new.options.type_references = true;
+        assert!(diff_reports(&old, &new).is_err());
+    }
+}
@@ -0,0 +1,65 @@
+    const expanded = new Set();
+
+    // Displayed node of an embedded node: its namespace truncated to the first segments, plus one
+    // segment for every expanded prefix
+    function displayedId(node) {
+        if (node.type !== 'namespace') return node.id;
+        const parts = node.id.split('.');
+        let length = Math.min(Math.max(drillDown.segments, 1), parts.length);
+        while (length < parts.length && expanded.has(parts.slice(0, length).join('.'))) length++;
+        return parts.slice(0, length).join('.');
+    }
+
+    function drillDownView() {
+        const nodes = [];
+        const byId = new Map();
+        const displayed = drillDown.nodes.map(node => {
+            const id = displayedId(node);
+            const viewNode = byId.get(id);
+            if (!viewNode) {
+                const info = id === node.id ? node : drillDown.prefixes[id];
+                const newNode = { id: id, name: id === node.id ? node.name : id, layer: info.layer, color: info.color, group: node.group, collapsible: false };
+                byId.set(id, newNode);
+                nodes.push(newNode);
+            } else if (viewNode.group !== node.group) {
+                viewNode.group = null;
+            }
+            return id;
+        });
+        drillDown.nodes.forEach((node, index) => {
+            if (displayed[index] !== node.id) byId.get(displayed[index]).collapsible = true;
+        });
+
+        const links = [];
+        const byPair = new Map();
+        drillDown.edges.forEach(edge => {
+            const source = displayed[edge.from];
+            const target = displayed[edge.to];
+            if (source === target) return;
+            const key = JSON.stringify([source, target]);
+            let link = byPair.get(key);
+            if (!link) {
+                const allowed = (drillDown.rules[byId.get(source).layer] || []).includes(byId.get(target).layer);
+                link = { source: source, target: target, weight: 0, allowed: allowed, merged: [], locations: new Set() };
+                byPair.set(key, link);
+                links.push(link);
+            }
+            // As EdgeInfo::merge: an edge only adds its weight when it brings new declarations
+            const added = edge.locations.filter(location => !link.locations.has(location));
+            added.forEach(location => link.locations.add(location));
+            if (edge.locations.length === 0 || added.length > 0) link.weight += edge.weight;
+            link.merged.push(edge.label);
+        });
+        links.forEach(link => {
+            if (!byId.get(link.source).collapsible && !byId.get(link.target).collapsible) return;
+            const lines = link.merged.slice(0, drillDown.maxTooltipEdges);
+            if (link.merged.length > lines.length) lines.push(`... and ${link.merged.length - lines.length} more`);
+            link.tooltip = lines.join('\n');
+        });
+        return { nodes: nodes, links: links };
+    }
+
+    function expandNode(id) {
+        expanded.add(id);
+        redraw();
+    }
@@ -0,0 +1,66 @@
+    let currentView = null;
+
+    function dotText(value) {
+        return String(value).replace(/\\/g, '\\\\').replace(/"/g, '\\"').replace(/\n/g, '\\n');
+    }
+
+    function penwidth(weight) {
+        return Math.round(Math.min(1 + Math.log2(Math.max(weight, 1)), 6) * 10) / 10;
+    }
+
+    function drillDownDot(view) {
+        const toggles = drillDown.toggles;
+        const visible = node => (toggles.show_recognized_nodes && node.layer !== 'unknown') || (toggles.show_unrecognized_nodes && node.layer === 'unknown');
+        const index = new Map(view.nodes.map((node, i) => [node.id, i + 1]));
+        const statement = (node, indent, shape) => {
+            const expandable = node.collapsible ? ', peripheries=2, tooltip="Click to expand"' : '';
+            return `${indent}P${index.get(node.id)} [label="${dotText(node.name)}", style=filled, fillcolor="${node.color}"${shape}${expandable}]`;
+        };
+        const owners = new Set(view.nodes.filter(node => node.group && node.group !== node.id && index.has(node.group)).map(node => node.group));
+        const clustered = node => owners.has(node.id) || (node.group && owners.has(node.group));
+
+        const lines = ['digraph G {', '\tnode [color=grey, style=filled];', '\tnode [fontname="Verdana", size="30,30"];'];
+        view.nodes.filter(node => !clustered(node) && visible(node)).forEach(node => lines.push(statement(node, '    ', '')));
+        owners.forEach(owner => {
+            lines.push(`    subgraph cluster_P${index.get(owner)} {`);
+            lines.push(`        label="${dotText(view.nodes[index.get(owner) - 1].name)}";`);
+            lines.push('        style="rounded,dashed";');
+            view.nodes.filter(node => (node.id === owner || node.group === owner) && visible(node))
+                .forEach(node => lines.push(statement(node, '        ', node.id === owner ? ', shape=box' : '')));
+            lines.push('    }');
+        });
+        view.links.forEach(link => {
+            if (!(link.allowed ? toggles.show_valid_dependencies : toggles.show_invalid_dependencies)) return;
+            const attributes = [];
+            if (!link.allowed) {
+                attributes.push('color="red" style=dotted');
+                attributes.push(`penwidth=${Math.max(penwidth(link.weight), 2)}`);
+            } else if (link.weight > 1) {
+                attributes.push(`penwidth=${penwidth(link.weight)}`);
+            }
+            if (link.weight > 1) attributes.push(`label="${link.weight}"`);
+            if (link.tooltip) attributes.push(`tooltip="${dotText(link.tooltip)}"`);
+            const list = attributes.length ? ` [${attributes.join(' ')}]` : '';
+            lines.push(`    P${index.get(link.source)} -> P${index.get(link.target)}${list}`);
+        });
+        lines.push(...drillDown.layerRules);
+        lines.push('}');
+        return lines.join('\n');
+    }
+
+    function redraw() {
+        currentView = drillDownView();
+        renderGraph(drillDownDot(currentView));
+    }
+
+    // Graphviz names the SVG node groups after the node statements: P1, P2...
+    function bindDrillDown(svg) {
+        svg.querySelectorAll('g.node').forEach(function(element) {
+            const title = element.querySelector('title').textContent;
+            const node = title.startsWith('P') ? currentView.nodes[parseInt(title.slice(1)) - 1] : null;
+            if (node && node.collapsible) {
+                element.style.cursor = 'pointer';
+                element.addEventListener('click', function() { expandNode(node.id); });
+            }
+        });
+    }
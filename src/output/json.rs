@@ -0,0 +1,314 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+use crate::core::analysis::StronglyConnectedComponent;
+use crate::core::dependencies::{NodeDependencies, SourceLocation};
+use crate::core::node::Node;
+
+/// Version of the JSON report layout. Bump it on any breaking change to the structs below.
+pub const JSON_REPORT_SCHEMA_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct JsonReport {
+    pub schema_version: u32,
+    pub generator: String,
+    pub generated_at: String,
+    pub analysis: String,
+    /// Analyzed folder. Project ids are paths under it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub root: Option<String>,
+    /// Command-line options the graph depends on. `diff` only compares reports with the same options.
+    #[serde(default)]
+    pub options: JsonReportOptions,
+    pub summary: JsonSummary,
+    pub nodes: Vec<JsonNode>,
+    pub edges: Vec<JsonEdge>,
+    pub layers: Vec<JsonNode>,
+    pub layer_rules: Vec<JsonLink>,
+    pub cycles: Vec<JsonCycle>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct JsonReportOptions {
+    /// Solution file (`--solution`), relative to `root` when under it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub solution: Option<String>,
+    /// MSBuild property overrides (`--property`).
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub properties: BTreeMap<String, String>,
+    /// Whether `--type-references` was given.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub type_references: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct JsonSummary {
+    pub nodes: usize,
+    pub edges: usize,
+    pub allowed_edges: usize,
+    pub disallowed_edges: usize,
+    pub unknown_nodes: usize,
+    pub layers: usize,
+    pub cycles: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct JsonNode {
+    pub id: String,
+    pub name: String,
+    pub layer: String,
+    pub node_type: String,
+    pub color: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct JsonEdge {
+    pub from: String,
+    pub to: String,
+    pub allowed: bool,
+    pub label: String,
+    /// Number of declarations contributing to the edge.
+    #[serde(default = "default_weight")]
+    pub weight: usize,
+    /// How the dependency is declared (`ProjectReference`, `using`, `global using`...), one entry per distinct kind.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub kinds: Vec<String>,
+    /// Types referenced through the edge, with `--type-references`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub types: Vec<String>,
+    /// Where the dependency is declared.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub locations: Vec<JsonLocation>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct JsonLocation {
+    pub file: String,
+    pub line: usize,
+    pub column: usize,
+    pub kind: String,
+    pub text: String,
+}
+
+impl From<&SourceLocation> for JsonLocation {
+    fn from(location: &SourceLocation) -> Self {
+        JsonLocation {
+            file: location.file.clone(),
+            line: location.line,
+            column: location.column,
+            kind: location.kind.clone(),
+            text: location.text.clone(),
+        }
+    }
+}
+
+fn default_weight() -> usize {
+    1
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct JsonLink {
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct JsonCycle {
+    pub members: Vec<String>,
+    pub cycle: Vec<String>,
+    pub closing_edges: Vec<JsonLink>,
+}
+
+impl From<&Node> for JsonNode {
+    fn from(node: &Node) -> Self {
+        JsonNode {
+            id: node.id.clone(),
+            name: node.name.clone(),
+            layer: node.layer.clone(),
+            node_type: node.node_type.clone(),
+            color: node.color.clone(),
+            group: node.group.clone(),
+        }
+    }
+}
+
+pub fn build_json_report(
+    analysis: &str,
+    root_path: &Path,
+    nodes: &[Node],
+    node_dependencies: &NodeDependencies,
+    layers: &[Node],
+    layer_dependencies: &NodeDependencies,
+    cycles: &[StronglyConnectedComponent],
+) -> JsonReport {
+    let edges: Vec<JsonEdge> = node_dependencies.iter().enumerate()
+        .flat_map(|(from, deps)| deps.iter().map(move |dep| (from, dep)))
+        .map(|(from, dep)| JsonEdge {
+            from: nodes[from].id.clone(),
+            to: nodes[dep.to].id.clone(),
+            allowed: dep.allowed,
+            label: dep.label.clone(),
+            weight: dep.weight,
+            kinds: dep.locations.iter()
+                .map(|location| location.kind.clone())
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .collect(),
+            types: dep.types.clone(),
+            locations: dep.locations.iter().map(JsonLocation::from).collect(),
+        })
+        .collect();
+
+    let layer_rules: Vec<JsonLink> = layer_dependencies.iter().enumerate()
+        .flat_map(|(from, deps)| deps.iter().map(move |dep| JsonLink {
+            from: layers[from].id.clone(),
+            to: layers[dep.to].id.clone(),
+        }))
+        .collect();
+
+    let ids = |indices: &[usize]| indices.iter().map(|&i| nodes[i].id.clone()).collect::<Vec<_>>();
+    let cycles: Vec<JsonCycle> = cycles.iter().map(|component| JsonCycle {
+        members: ids(&component.members),
+        cycle: ids(&component.cycle),
+        closing_edges: component.closing_edges.iter()
+            .map(|&(from, to)| JsonLink { from: nodes[from].id.clone(), to: nodes[to].id.clone() })
+            .collect(),
+    }).collect();
+
+    let summary = JsonSummary {
+        nodes: nodes.len(),
+        edges: edges.len(),
+        allowed_edges: edges.iter().filter(|e| e.allowed).count(),
+        disallowed_edges: edges.iter().filter(|e| !e.allowed).count(),
+        unknown_nodes: nodes.iter().filter(|n| n.layer == "unknown").count(),
+        layers: layers.len(),
+        cycles: cycles.len(),
+    };
+
+    JsonReport {
+        schema_version: JSON_REPORT_SCHEMA_VERSION,
+        generator: "depscop".to_string(),
+        generated_at: Utc::now().to_rfc3339(),
+        analysis: analysis.to_string(),
+        root: Some(root_path.to_string_lossy().into_owned()),
+        options: JsonReportOptions::default(),
+        summary,
+        nodes: nodes.iter().map(JsonNode::from).collect(),
+        edges,
+        layers: layers.iter().map(JsonNode::from).collect(),
+        layer_rules,
+        cycles,
+    }
+}
+
+/// Writes any JSON document (report, SARIF log...) to `path`, or to stdout when no path is given.
+pub fn write_json_output<T: Serialize>(document: &T, path: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
+    match path {
+        Some(path) => {
+            let file = File::create(path)?;
+            serde_json::to_writer_pretty(file, document)?;
+        }
+        None => {
+            let mut stdout = io::stdout().lock();
+            serde_json::to_writer_pretty(&mut stdout, document)?;
+            writeln!(stdout)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::analysis::detect_cycles;
+    use crate::core::dependencies::EdgeInfo;
+
+    fn node(id: &str, layer: &str) -> Node {
+        Node {
+            id: id.to_string(),
+            name: id.rsplit('/').next().unwrap_or(id).to_string(),
+            layer: layer.to_string(),
+            node_type: "project".to_string(),
+            color: "#ffffff".to_string(),
+            group: None,
+        }
+    }
+
+    fn edge(to: usize, allowed: bool) -> EdgeInfo {
+        EdgeInfo {
+            to,
+            allowed,
+            label: String::new(),
+            weight: 2,
+            locations: vec![SourceLocation {
+                file: "/repo/Web/Web.csproj".to_string(),
+                line: 4,
+                column: 5,
+                end_line: 4,
+                kind: "ProjectReference".to_string(),
+                text: "<ProjectReference Include=\"..\\Data\\Data.csproj\" />".to_string(),
+            }],
+            types: vec!["Shop.Data.Order".to_string()],
+            merged: Vec::new(),
+        }
+    }
+
+    fn report() -> JsonReport {
+        let nodes = [node("/repo/Web/Web.csproj", "web"), node("/repo/Data/Data.csproj", "data")];
+        let node_dependencies = vec![vec![edge(1, true)], vec![edge(0, false)]];
+        let layers = [node("web", "web"), node("data", "data")];
+        let layer_dependencies = vec![vec![edge(1, true)], Vec::new()];
+        let cycles = detect_cycles(&node_dependencies);
+        build_json_report("csharp:projects", Path::new("/repo"), &nodes, &node_dependencies, &layers, &layer_dependencies, &cycles)
+    }
+
+    #[test]
+    fn report_follows_schema_version_1() {
+        let value = serde_json::to_value(report()).unwrap();
+        assert_eq!(value["schema_version"], 1);
+        assert_eq!(value["root"], "/repo");
+        assert_eq!(value["options"], serde_json::json!({}));
+        assert_eq!(value["summary"], serde_json::json!({
+            "nodes": 2, "edges": 2, "allowed_edges": 1, "disallowed_edges": 1, "unknown_nodes": 0, "layers": 2, "cycles": 1,
+        }));
+        assert_eq!(value["edges"][0]["kinds"], serde_json::json!(["ProjectReference"]));
+        assert_eq!(value["edges"][0]["locations"][0]["line"], 4);
+        assert_eq!(value["layer_rules"], serde_json::json!([{ "from": "web", "to": "data" }]));
+        assert_eq!(value["cycles"][0]["members"], serde_json::json!(["/repo/Web/Web.csproj", "/repo/Data/Data.csproj"]));
+        assert!(value["nodes"][0].get("group").is_none());
+    }
+
+    #[test]
+    fn report_round_trips_with_options() {
+        let mut original = report();
+        original.options = JsonReportOptions {
+            solution: Some("Shop.sln".to_string()),
+            properties: BTreeMap::from([("Configuration".to_string(), "Release".to_string())]),
+            type_references: true,
+        };
+        let text = serde_json::to_string(&original).unwrap();
+        let parsed: JsonReport = serde_json::from_str(&text).unwrap();
+        assert_eq!(parsed.options, original.options);
+        assert_eq!(serde_json::to_string(&parsed).unwrap(), text);
+    }
+
+    #[test]
+    fn optional_fields_default_when_missing() {
+        let mut value = serde_json::to_value(report()).unwrap();
+        let object = value.as_object_mut().unwrap();
+        object.remove("options");
+        object.remove("root");
+        object["edges"][0].as_object_mut().unwrap().retain(|key, _| ["from", "to", "allowed", "label"].contains(&key.as_str()));
+        let parsed: JsonReport = serde_json::from_value(value).unwrap();
+        assert_eq!(parsed.options, JsonReportOptions::default());
+        assert_eq!(parsed.root, None);
+        assert_eq!(parsed.edges[0].weight, 1);
+        assert!(parsed.edges[0].locations.is_empty());
+    }
+}
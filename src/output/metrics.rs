@@ -0,0 +1,88 @@
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+use crate::core::metrics::NodeMetrics;
+
+fn format_ratio(value: Option<f64>) -> String {
+    value.map(|value| format!("{:.2}", value)).unwrap_or_else(|| "-".to_string())
+}
+
+pub fn display_metrics(out: &mut dyn Write, metrics: &[NodeMetrics]) -> std::io::Result<()> {
+    let width = metrics.iter().map(|m| m.name.len()).max().unwrap_or(0).max("Node".len());
+    writeln!(out, "\nMetrics:")?;
+    writeln!(out, "{:<width$}  {:>4}  {:>4}  {:>5}  {:>5}  {:>5}", "Node", "Ca", "Ce", "I", "A", "D")?;
+    for m in metrics {
+        writeln!(
+            out,
+            "{:<width$}  {:>4}  {:>4}  {:>5}  {:>5}  {:>5}",
+            m.name, m.afferent, m.efferent, format_ratio(Some(m.instability)), format_ratio(m.abstractness), format_ratio(m.distance)
+        )?;
+    }
+    Ok(())
+}
+
+/// Writes the metrics as CSV or JSON, depending on the extension of `path`.
+pub fn write_metrics_file(metrics: &[NodeMetrics], path: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let extension = Path::new(path).extension().and_then(|e| e.to_str()).unwrap_or_default().to_lowercase();
+    match extension.as_str() {
+        "csv" => {
+            let mut file = File::create(path)?;
+            writeln!(file, "id,name,layer,afferent,efferent,instability,abstractness,distance")?;
+            let optional = |value: Option<f64>| value.map(|value| value.to_string()).unwrap_or_default();
+            for m in metrics {
+                writeln!(
+                    file, "{},{},{},{},{},{},{},{}",
+                    csv_field(&m.id), csv_field(&m.name), csv_field(&m.layer), m.afferent, m.efferent,
+                    m.instability, optional(m.abstractness), optional(m.distance)
+                )?;
+            }
+        }
+        "json" => {
+            let file = File::create(path)?;
+            serde_json::to_writer_pretty(file, metrics)?;
+        }
+        _ => return Err(Box::from(format!("Unsupported metrics file '{}': use a .csv or .json extension", path))),
+    }
+    Ok(())
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Abstractness/instability scatter chart, as an inline SVG with the main sequence (A + I = 1).
+/// Nodes without abstractness are left out.
+pub fn generate_metrics_chart(file: &mut File, metrics: &[NodeMetrics]) -> Result<(), Box<dyn std::error::Error>> {
+    const SIZE: f64 = 400.0;
+    const MARGIN: f64 = 40.0;
+    let x = |instability: f64| MARGIN + instability * SIZE;
+    let y = |abstractness: f64| MARGIN + (1.0 - abstractness) * SIZE;
+
+    writeln!(file, "    <div class=\"metrics-chart\" style=\"text-align: center; padding: 20px;\">")?;
+    writeln!(file, "        <h2>Abstractness vs. instability</h2>")?;
+    writeln!(file, "        <svg width=\"{0}\" height=\"{0}\" style=\"font-size: 11px;\">", SIZE + 2.0 * MARGIN)?;
+    writeln!(file, "            <rect x=\"{0}\" y=\"{0}\" width=\"{1}\" height=\"{1}\" fill=\"none\" stroke=\"#999\"/>", MARGIN, SIZE)?;
+    writeln!(file, "            <line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"#667eea\" stroke-dasharray=\"4\"/>", x(0.0), y(1.0), x(1.0), y(0.0))?;
+    writeln!(file, "            <text x=\"{}\" y=\"{}\" text-anchor=\"middle\">Instability (I)</text>", x(0.5), y(0.0) + 30.0)?;
+    writeln!(file, "            <text x=\"{}\" y=\"{}\" text-anchor=\"middle\" transform=\"rotate(-90 {} {})\">Abstractness (A)</text>", x(0.0) - 25.0, y(0.5), x(0.0) - 25.0, y(0.5))?;
+    for m in metrics {
+        let Some(abstractness) = m.abstractness else { continue };
+        writeln!(
+            file,
+            "            <circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"5\" fill=\"steelblue\" fill-opacity=\"0.7\"><title>{} (I={:.2}, A={:.2}, D={})</title></circle>",
+            x(m.instability), y(abstractness), html_escape(&m.name), m.instability, abstractness, format_ratio(m.distance)
+        )?;
+    }
+    writeln!(file, "        </svg>")?;
+    writeln!(file, "    </div>")?;
+    Ok(())
+}
+
+fn html_escape(value: &str) -> String {
+    value.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
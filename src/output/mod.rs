@@ -2,3 +2,7 @@ pub mod static_output;
 pub mod html;
 pub mod graphviz;
 pub mod mermaid;
+pub mod json;
+pub mod sarif;
+pub mod metrics;
+pub mod diff;
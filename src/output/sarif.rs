@@ -0,0 +1,235 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use path_slash::PathExt;
+use serde_json::{json, Value};
+
+use crate::core::dependencies::{NodeDependencies, SourceLocation};
+use crate::core::node::Node;
+
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+const SOURCE_ROOT: &str = "SRCROOT";
+
+/// Rule id reported for every disallowed edge going from `from_layer` to `to_layer`.
+pub fn layer_rule_id(from_layer: &str, to_layer: &str) -> String {
+    format!("layer/{}-to-{}", from_layer, to_layer)
+}
+
+/// Builds a SARIF 2.1.0 log with one result per disallowed edge. File locations are made
+/// relative to `source_root`, normally the repository root, so code-scanning services can map
+/// them to the repository whatever folder was analyzed.
+pub fn build_sarif_report(
+    nodes: &[Node],
+    node_dependencies: &NodeDependencies,
+    rules: &HashMap<String, Vec<String>>,
+    source_root: &Path,
+) -> Value {
+    // BTreeMap keeps the rule order stable between runs
+    let mut rule_indices: BTreeMap<(String, String), usize> = BTreeMap::new();
+    for (from, deps) in node_dependencies.iter().enumerate() {
+        for dep in deps.iter().filter(|dep| !dep.allowed) {
+            rule_indices.insert((nodes[from].layer.clone(), nodes[dep.to].layer.clone()), 0);
+        }
+    }
+    for (index, value) in rule_indices.values_mut().enumerate() {
+        *value = index;
+    }
+
+    let sarif_rules: Vec<Value> = rule_indices.keys().map(|(from_layer, to_layer)| {
+        let allowed = rules.get(from_layer).map(|layers| layers.join(", ")).unwrap_or_default();
+        json!({
+            "id": layer_rule_id(from_layer, to_layer),
+            "name": "LayerDependencyViolation",
+            "shortDescription": {
+                "text": format!("Layer '{}' must not depend on layer '{}'", from_layer, to_layer)
+            },
+            "fullDescription": {
+                "text": format!("Nodes in layer '{}' may only depend on the layers [{}].", from_layer, allowed)
+            },
+            "defaultConfiguration": { "level": "error" }
+        })
+    }).collect();
+
+    let mut results = Vec::new();
+    for (from, deps) in node_dependencies.iter().enumerate() {
+        for dep in deps.iter().filter(|dep| !dep.allowed) {
+            let from_node = &nodes[from];
+            let to_node = &nodes[dep.to];
+            let key = (from_node.layer.clone(), to_node.layer.clone());
+            let locations: Vec<Value> = dep.locations.iter()
+                .map(|location| physical_location(location, source_root))
+                .collect();
+            results.push(json!({
+                "ruleId": layer_rule_id(&key.0, &key.1),
+                "ruleIndex": rule_indices[&key],
+                "level": "error",
+                "message": {
+                    "text": format!(
+                        "'{}' ({}) depends on '{}' ({}), which is not allowed by the layer rules.",
+                        from_node.name, from_node.layer, to_node.name, to_node.layer
+                    )
+                },
+                "locations": locations
+            }));
+        }
+    }
+
+    json!({
+        "$schema": SARIF_SCHEMA,
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "depscop",
+                    "informationUri": "https://github.com/tecnocrata/deps-cop",
+                    "rules": sarif_rules
+                }
+            },
+            "originalUriBaseIds": {
+                SOURCE_ROOT: { "uri": format!("{}/", file_uri(source_root).trim_end_matches('/')) }
+            },
+            "results": results
+        }]
+    })
+}
+
+fn physical_location(location: &SourceLocation, source_root: &Path) -> Value {
+    let file = Path::new(&location.file);
+    let artifact_location = match file.strip_prefix(source_root) {
+        Ok(relative) => json!({ "uri": encode_path(&relative.to_slash_lossy()), "uriBaseId": SOURCE_ROOT }),
+        Err(_) => json!({ "uri": file_uri(file) }),
+    };
+    json!({
+        "physicalLocation": {
+            "artifactLocation": artifact_location,
+            "region": {
+                "startLine": location.line,
+                "startColumn": location.column,
+                "snippet": { "text": location.text }
+            }
+        }
+    })
+}
+
+fn file_uri(path: &Path) -> String {
+    let path = encode_path(&path.to_slash_lossy());
+    if path.starts_with('/') {
+        format!("file://{}", path)
+    } else {
+        // Windows drive paths need an extra slash: file:///C:/...
+        format!("file:///{}", path)
+    }
+}
+
+// Percent-encodes every byte of a slash-separated path that is not allowed as is in a URI path
+// segment, e.g. spaces and `#`. The drive colon of Windows paths is kept.
+fn encode_path(path: &str) -> String {
+    let mut encoded = String::with_capacity(path.len());
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' | b':' => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::dependencies::EdgeInfo;
+
+    fn node(id: &str, name: &str, layer: &str) -> Node {
+        Node {
+            id: id.to_string(),
+            name: name.to_string(),
+            layer: layer.to_string(),
+            node_type: "project".to_string(),
+            color: String::new(),
+            group: None,
+        }
+    }
+
+    fn edge(to: usize, allowed: bool, files: &[&str]) -> EdgeInfo {
+        EdgeInfo {
+            to,
+            allowed,
+            label: String::new(),
+            weight: files.len(),
+            locations: files.iter()
+                .map(|file| SourceLocation {
+                    file: file.to_string(),
+                    line: 7,
+                    column: 5,
+                    end_line: 7,
+                    kind: "ProjectReference".to_string(),
+                    text: "<ProjectReference Include=\"..\\Web\\Web.csproj\" />".to_string(),
+                })
+                .collect(),
+            types: Vec::new(),
+            merged: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn report_snapshot() {
+        let nodes = [
+            node("/repo/my app/Core #1/Core.csproj", "Core.csproj", "core"),
+            node("/repo/my app/Web/Web.csproj", "Web.csproj", "web"),
+        ];
+        let node_dependencies = vec![
+            vec![edge(1, false, &["/repo/my app/Core #1/Core.csproj", "/elsewhere/Shared.props"])],
+            vec![edge(0, true, &["/repo/my app/Web/Web.csproj"])],
+        ];
+        let rules = HashMap::from([("core".to_string(), Vec::new()), ("web".to_string(), vec!["core".to_string()])]);
+        let report = build_sarif_report(&nodes, &node_dependencies, &rules, Path::new("/repo"));
+
+        let snippet = "<ProjectReference Include=\"..\\Web\\Web.csproj\" />";
+        let expected = json!({
+            "$schema": SARIF_SCHEMA,
+            "version": "2.1.0",
+            "runs": [{
+                "tool": {
+                    "driver": {
+                        "name": "depscop",
+                        "informationUri": "https://github.com/tecnocrata/deps-cop",
+                        "rules": [{
+                            "id": "layer/core-to-web",
+                            "name": "LayerDependencyViolation",
+                            "shortDescription": { "text": "Layer 'core' must not depend on layer 'web'" },
+                            "fullDescription": { "text": "Nodes in layer 'core' may only depend on the layers []." },
+                            "defaultConfiguration": { "level": "error" }
+                        }]
+                    }
+                },
+                "originalUriBaseIds": { "SRCROOT": { "uri": "file:///repo/" } },
+                "results": [{
+                    "ruleId": "layer/core-to-web",
+                    "ruleIndex": 0,
+                    "level": "error",
+                    "message": { "text": "'Core.csproj' (core) depends on 'Web.csproj' (web), which is not allowed by the layer rules." },
+                    "locations": [
+                        {
+                            "physicalLocation": {
+                                "artifactLocation": { "uri": "my%20app/Core%20%231/Core.csproj", "uriBaseId": "SRCROOT" },
+                                "region": { "startLine": 7, "startColumn": 5, "snippet": { "text": snippet } }
+                            }
+                        },
+                        {
+                            "physicalLocation": {
+                                "artifactLocation": { "uri": "file:///elsewhere/Shared.props" },
+                                "region": { "startLine": 7, "startColumn": 5, "snippet": { "text": snippet } }
+                            }
+                        }
+                    ]
+                }]
+            }]
+        });
+        assert_eq!(report, expected);
+    }
+
+    #[test]
+    fn file_uris_are_encoded() {
+        assert_eq!(file_uri(Path::new("/a b/c#d/é.cs")), "file:///a%20b/c%23d/%C3%A9.cs");
+        assert_eq!(file_uri(Path::new("C:/repo/x%y.cs")), "file:///C:/repo/x%25y.cs");
+    }
+}
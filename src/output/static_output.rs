@@ -1,23 +1,49 @@
+use std::collections::{BTreeMap, HashMap};
 use std::fs::File;
 use std::io::Write;
 use chrono::Local;
 
+use crate::analyzers::csharp::combined::{ReferenceReport, UnusedReference};
 use crate::config::types::Toggles;
+use crate::core::analysis::StronglyConnectedComponent;
+use crate::core::build_order::BuildOrder;
+use crate::core::check::{CheckResult, EXIT_CYCLES, EXIT_DISALLOWED_EDGES, EXIT_UNKNOWN_NODES, EXIT_UNSTABLE_DEPENDENCIES};
+use crate::core::impact::ImpactedNode;
+use crate::core::metrics::NodeMetrics;
 use crate::core::node::Node;
-use crate::core::dependencies::NodeDependencies;
+use crate::output::metrics::generate_metrics_chart;
+use crate::core::dependencies::{EdgeInfo, NodeDependencies, SourceLocation};
+
+/// Uncollapsed graph embedded in the HTML output, so that collapsed namespaces can be expanded
+/// by clicking them.
+pub struct DrillDown<'a> {
+    pub nodes: &'a [Node],
+    pub node_dependencies: &'a NodeDependencies,
+    /// Nodes of the truncated namespace names, with their own layer and color.
+    pub prefixes: Vec<Node>,
+    pub segments: usize,
+    pub rules: &'a HashMap<String, Vec<String>>,
+}
+
+/// Where and how the HTML page is generated.
+pub struct HtmlOptions<'a> {
+    pub path: &'a str,
+    /// `graphviz` or `d3`.
+    pub format: &'a str,
+    pub toggles: &'a Toggles,
+    /// Metrics drawn as an A/I chart below the graph.
+    pub metrics: Option<&'a [NodeMetrics]>,
+    pub drill_down: Option<&'a DrillDown<'a>>,
+}
 
 pub fn generate_html_output(
     nodes: &[Node],
     node_dependencies: &NodeDependencies,
     layers: &[Node],
     layer_dependencies: &NodeDependencies,
-    path: &str,
-    format: &str,
-    toggles: &Toggles
+    options: &HtmlOptions
 ) -> Result<(), Box<dyn std::error::Error>> {
-    println!("Generating HTML output at '{}' using format '{}'", path, format);
-
-    let mut file = File::create(path)?;
+    let mut file = File::create(options.path)?;
     let now = Local::now();
 
     writeln!(file, "<!DOCTYPE html>")?;
@@ -35,7 +61,7 @@ pub fn generate_html_output(
     writeln!(file, "    .footer {{ background-color: #718096; color: #ffffff; text-align: center; padding: 10px; flex-shrink: 0; }}")?;
     writeln!(file, "    .rust-logo {{ height: 50px; }}")?;
     writeln!(file, "</style>")?;
-    generate_header_content(&mut file, format)?;
+    generate_header_content(&mut file, options.format)?;
     writeln!(file, "</head>")?;
     writeln!(file, "<body>")?;
     writeln!(file, "    <div class=\"header\">")?;
@@ -43,14 +69,17 @@ pub fn generate_html_output(
     writeln!(file, "        <p>This page was generated automatically.</p>")?;
     writeln!(file, "    </div>")?;
     writeln!(file, "<div class=\"content\">")?;
-    generate_body_content(&mut file, format, nodes, node_dependencies)?;
+    generate_body_content(&mut file, options.format, nodes, node_dependencies)?;
     writeln!(file, "        </div>")?;
+    if let Some(metrics) = options.metrics {
+        generate_metrics_chart(&mut file, metrics)?;
+    }
     writeln!(file, "    <div class=\"footer\">")?;
     writeln!(file, "        <p>Generated on: {}</p>", now.format("%Y-%m-%dT%H:%M:%SZ"))?;
     writeln!(file, "        <p>Everything was generated using Rust.</p>")?;
     writeln!(file, "        <img src=\"https://www.rust-lang.org/logos/rust-logo-blk.svg\" alt=\"Rust Logo\" class=\"rust-logo mx-auto\">")?;
     writeln!(file, "    </div>")?;
-    generate_script_code(&mut file, format, nodes, node_dependencies, layers, layer_dependencies, toggles)?;
+    generate_script_code(&mut file, nodes, node_dependencies, layers, layer_dependencies, options)?;
     writeln!(file, "</body>")?;
     writeln!(file, "</html>")?;
 
@@ -82,54 +111,126 @@ fn generate_style_content_d3(file: &mut File) -> Result<(), Box<dyn std::error::
     Ok(())
 }
 
-fn generate_script_code(file: &mut File, format: &str, nodes: &[Node], node_dependencies: &NodeDependencies, layers: &[Node], layer_dependencies: &NodeDependencies, toggles: &Toggles) -> Result<(), Box<dyn std::error::Error>> {
-    match format {
-        "graphviz" => generate_script_code_graphviz(file, nodes, node_dependencies, layers, layer_dependencies, toggles)?,
-        "d3" => generate_script_code_d3(file, nodes, node_dependencies)?,
+fn generate_script_code(file: &mut File, nodes: &[Node], node_dependencies: &NodeDependencies, layers: &[Node], layer_dependencies: &NodeDependencies, options: &HtmlOptions) -> Result<(), Box<dyn std::error::Error>> {
+    match options.format {
+        "graphviz" => generate_script_code_graphviz(file, nodes, node_dependencies, layers, layer_dependencies, options.toggles, options.drill_down)?,
+        "d3" => generate_script_code_d3(file, nodes, node_dependencies, options.drill_down)?,
         _ => (),
     }
     Ok(())
 }
 
-fn generate_script_code_graphviz(file: &mut File, nodes: &[Node], node_dependencies: &NodeDependencies, layers: &[Node], layer_dependencies: &NodeDependencies, toggles: &Toggles) -> Result<(), Box<dyn std::error::Error>> {
-    writeln!(file, "<script>")?;
-    writeln!(file, "    var viz = new Viz();")?;
-    writeln!(file, "    var graphvizData = `")?;
-    writeln!(file, "digraph G {{")?;
-    writeln!(file, "\tnode [color=grey, style=filled];")?;
-    writeln!(file, "\tnode [fontname=\"Verdana\", size=\"30,30\"];")?;
-    for (index, node) in nodes.iter().enumerate() {
-        if (toggles.show_recognized_nodes && node.layer != "unknown") || (toggles.show_unrecognized_nodes && node.layer == "unknown") {
-            writeln!(file, "    P{} [label=\"{}\", style=filled, fillcolor=\"{}\"]", index + 1, node.name, node.color)?;
-        }
-    }
-    for (index, deps) in node_dependencies.iter().enumerate() {
-        for dep in deps {
-            if dep.allowed && toggles.show_valid_dependencies {
-                writeln!(file, "    P{} -> P{}", index + 1, dep.to + 1)?;
-            } else if !dep.allowed && toggles.show_invalid_dependencies {
-                writeln!(file, "    P{} -> P{} [color=\"red\" style=dotted penwidth=2]", index + 1, dep.to + 1)?;
-            }
-        }
+// Client-side model of the drill-down: `drillDownView()` merges the embedded nodes according to
+// the expanded namespaces, as `collapse_namespaces` does (edge weights included, following
+// `EdgeInfo::merge`), and `redraw()` renders it again
+const DRILL_DOWN_SCRIPT: &str = include_str!("html/drill_down.js");
+
+// Graphviz source of the drill-down view, drawn like the static Graphviz output
+const DRILL_DOWN_GRAPHVIZ_SCRIPT: &str = include_str!("html/drill_down_graphviz.js");
+
+// Embedded graph of the drill-down, as a JavaScript object
+fn write_drill_down_model(file: &mut File, drill_down: &DrillDown, extra: serde_json::Value) -> Result<(), Box<dyn std::error::Error>> {
+    let nodes: Vec<serde_json::Value> = drill_down.nodes.iter()
+        .map(|node| serde_json::json!({
+            "id": node.id,
+            "name": node.name,
+            "layer": node.layer,
+            "color": node.color,
+            "type": node.node_type,
+            "group": node.group,
+        }))
+        .collect();
+    let edges: Vec<serde_json::Value> = drill_down.node_dependencies.iter().enumerate()
+        .flat_map(|(from, edges)| edges.iter().map(move |edge| serde_json::json!({
+            "from": from,
+            "to": edge.to,
+            "weight": edge.weight,
+            "label": edge.label,
+            // Identifies the declarations, so merged edges count each of them once
+            "locations": edge.locations.iter()
+                .map(|location| format!("{}:{}:{}:{}", location.file, location.line, location.column, location.kind))
+                .collect::<Vec<_>>(),
+        })))
+        .collect();
+    let prefixes: serde_json::Map<String, serde_json::Value> = drill_down.prefixes.iter()
+        .map(|node| (node.id.clone(), serde_json::json!({ "layer": node.layer, "color": node.color })))
+        .collect();
+    let mut model = serde_json::json!({
+        "segments": drill_down.segments,
+        "maxTooltipEdges": MAX_TOOLTIP_LOCATIONS,
+        "nodes": nodes,
+        "edges": edges,
+        "prefixes": prefixes,
+        "rules": drill_down.rules,
+    });
+    if let (Some(model), serde_json::Value::Object(extra)) = (model.as_object_mut(), extra) {
+        model.extend(extra);
     }
+    // JSON is valid JavaScript; "</" is escaped so the data cannot close the script element
+    writeln!(file, "    const drillDown = {};", serde_json::to_string(&model)?.replace("</", "<\\/"))?;
+    writeln!(file, "{}", DRILL_DOWN_SCRIPT)?;
+    Ok(())
+}
 
-    writeln!(file, "\tsubgraph cluster_key {{")?;
-    writeln!(file, "\t\tlabel=\"Layer Rules\";")?;
+// Node and edge statements of the static "Layer Rules" cluster
+fn graphviz_layer_rules(layers: &[Node], layer_dependencies: &NodeDependencies) -> Vec<String> {
+    let mut lines = vec!["\tsubgraph cluster_key {".to_string(), "\t\tlabel=\"Layer Rules\";".to_string()];
     for (index, layer) in layers.iter().enumerate() {
-        writeln!(file, "    L{} [label=\"{}\", style=filled, fillcolor=\"{}\"]", index + 1, layer.name, layer.color)?;
+        lines.push(format!("    L{} [label=\"{}\", style=filled, fillcolor=\"{}\"]", index + 1, dot_string(&layer.name), dot_string(&layer.color)));
     }
     for (index, deps) in layer_dependencies.iter().enumerate() {
         for dep in deps {
-            writeln!(file, "    L{} -> L{}", index + 1, dep.to + 1)?;
+            lines.push(format!("    L{} -> L{}", index + 1, dep.to + 1));
         }
     }
-    writeln!(file, "\t}}")?;
+    lines.push("\t}".to_string());
+    lines
+}
+
+fn generate_script_code_graphviz(file: &mut File, nodes: &[Node], node_dependencies: &NodeDependencies, layers: &[Node], layer_dependencies: &NodeDependencies, toggles: &Toggles, drill_down: Option<&DrillDown>) -> Result<(), Box<dyn std::error::Error>> {
+    writeln!(file, "<script>")?;
+    writeln!(file, "    var viz = new Viz();")?;
+    if let Some(drill_down) = drill_down {
+        let extra = serde_json::json!({ "toggles": toggles, "layerRules": graphviz_layer_rules(layers, layer_dependencies) });
+        write_drill_down_model(file, drill_down, extra)?;
+        writeln!(file, "{}", DRILL_DOWN_GRAPHVIZ_SCRIPT)?;
+    } else {
+        let mut lines = vec![
+            "digraph G {".to_string(),
+            "\tnode [color=grey, style=filled];".to_string(),
+            "\tnode [fontname=\"Verdana\", size=\"30,30\"];".to_string(),
+        ];
+        lines.extend(graphviz_nodes(nodes, toggles));
+        for (index, deps) in node_dependencies.iter().enumerate() {
+            for dep in deps {
+                let visible = if dep.allowed { toggles.show_valid_dependencies } else { toggles.show_invalid_dependencies };
+                if visible {
+                    lines.push(format!("    P{} -> P{}{}", index + 1, dep.to + 1, graphviz_edge_attributes(dep, "dotted", true)));
+                }
+            }
+        }
+        lines.extend(graphviz_layer_rules(layers, layer_dependencies));
+        lines.push("}".to_string());
+        writeln!(file, "    var graphvizData = {};", js_string(&lines.join("\n")))?;
+    }
 
-    writeln!(file, "}}`;")?;
-    writeln!(file, "    viz.renderSVGElement(graphvizData)")?;
+    // Registered once: the drill-down renders the graph again on every click
+    writeln!(file, "    function resizeSvg() {{")?;
+    writeln!(file, "        var svg = document.querySelector('#graph svg');")?;
+    writeln!(file, "        if (!svg) return;")?;
+    writeln!(file, "        var container = document.getElementById('graph-container');")?;
+    writeln!(file, "        svg.style.width = container.clientWidth + 'px';")?;
+    writeln!(file, "        svg.style.height = container.clientHeight + 'px';")?;
+    writeln!(file, "    }}")?;
+    writeln!(file, "    window.addEventListener('resize', resizeSvg);")?;
+    writeln!(file, "    function renderGraph(graphvizData) {{")?;
+    writeln!(file, "        viz.renderSVGElement(graphvizData)")?;
     writeln!(file, "            .then(function(element) {{")?;
     writeln!(file, "                var graph = document.getElementById('graph');")?;
-    writeln!(file, "                graph.appendChild(element);")?;
+    writeln!(file, "                graph.replaceChildren(element);")?;
+    if drill_down.is_some() {
+        writeln!(file, "                bindDrillDown(element);")?;
+    }
     writeln!(file, "                var svg = graph.querySelector('svg');")?;
     writeln!(file, "                svg.setAttribute('preserveAspectRatio', 'none');")?;
     writeln!(file, "                svg.style.width = '100%';")?;
@@ -142,22 +243,22 @@ fn generate_script_code_graphviz(file: &mut File, nodes: &[Node], node_dependenc
     writeln!(file, "                    minZoom: 0.5,")?;
     writeln!(file, "                    maxZoom: 10")?;
     writeln!(file, "                }});")?;
-    writeln!(file, "                function resizeSvg() {{")?;
-    writeln!(file, "                    var container = document.getElementById('graph-container');")?;
-    writeln!(file, "                    svg.style.width = container.clientWidth + 'px';")?;
-    writeln!(file, "                    svg.style.height = container.clientHeight + 'px';")?;
-    writeln!(file, "                }}")?;
-    writeln!(file, "                window.addEventListener('resize', resizeSvg);")?;
     writeln!(file, "                resizeSvg();")?;
     writeln!(file, "            }})")?;
     writeln!(file, "            .catch(error => {{")?;
     writeln!(file, "                console.error('Error rendering graph:', error);")?;
     writeln!(file, "            }});")?;
+    writeln!(file, "    }}")?;
+    if drill_down.is_some() {
+        writeln!(file, "    redraw();")?;
+    } else {
+        writeln!(file, "    renderGraph(graphvizData);")?;
+    }
     writeln!(file, "</script>")?;
     Ok(())
 }
 
-fn generate_script_code_d3(file: &mut File, nodes: &[Node], node_dependencies: &NodeDependencies) -> Result<(), Box<dyn std::error::Error>>  {
+fn generate_script_code_d3(file: &mut File, nodes: &[Node], node_dependencies: &NodeDependencies, drill_down: Option<&DrillDown>) -> Result<(), Box<dyn std::error::Error>>  {
     writeln!(file, "<script src=\"https://d3js.org/d3.v6.min.js\"></script>")?;
     writeln!(file, "<script>")?;
     writeln!(file, "    const svg = d3.select('svg'),")?;
@@ -180,62 +281,93 @@ fn generate_script_code_d3(file: &mut File, nodes: &[Node], node_dependencies: &
     writeln!(file, "        .attr('d', 'M0,-5L10,0L0,5')")?;
     writeln!(file, "        .attr('fill', '#999');")?;
 
-    writeln!(file, "    const nodes = [")?;
-    for node in nodes {
-        writeln!(file, "        {{ id: '{}', name: '{}', color: '{}' }},", node.id, node.name, node.color)?;
-    }
-    writeln!(file, "    ];")?;
+    if let Some(drill_down) = drill_down {
+        write_drill_down_model(file, drill_down, serde_json::json!({}))?;
+        writeln!(file, "    function redraw() {{")?;
+        writeln!(file, "        const view = drillDownView();")?;
+        writeln!(file, "        render(view.nodes, view.links);")?;
+        writeln!(file, "    }}")?;
+    } else {
+        writeln!(file, "    const nodes = [")?;
+        for node in nodes {
+            writeln!(file, "        {{ id: {}, name: {}, color: {} }},", js_string(&node.id), js_string(&node.name), js_string(&node.color))?;
+        }
+        writeln!(file, "    ];")?;
 
-    writeln!(file, "    const links = [")?;
-    for (index, dependencies) in node_dependencies.iter().enumerate() {
-        for target_index in dependencies.iter() {
-            writeln!(file, "        {{ source: '{}', target: '{}' }},", nodes[index].id, nodes[target_index.to].id)?;
+        writeln!(file, "    const links = [")?;
+        for (index, dependencies) in node_dependencies.iter().enumerate() {
+            for target_index in dependencies.iter() {
+                writeln!(file, "        {{ source: {}, target: {}, weight: {}, tooltip: {} }},", js_string(&nodes[index].id), js_string(&nodes[target_index.to].id), target_index.weight, js_string(&edge_tooltip(target_index)))?;
+            }
         }
+        writeln!(file, "    ];")?;
     }
-    writeln!(file, "    ];")?;
 
     writeln!(file, r#"
-        nodes.forEach(node => {{
-            node.incomingLinks = links.filter(link => link.target === node.id).length;
-        }});
-
-        const simulation = d3.forceSimulation(nodes)
-            .force("link", d3.forceLink(links).id(d => d.id).distance(200))
-            .force("charge", d3.forceManyBody().strength(-500))
-            .force("center", d3.forceCenter(width / 2, height / 2));
-
-        const link = g.selectAll(".link")
-            .data(links)
-            .join("line")
-            .classed("link", true)
-            .attr("stroke-width", 2);
-
-        const node = g.selectAll(".node")
-            .data(nodes)
-            .join("g")
-            .classed("node", true)
-            .call(d3.drag()
-                .on("start", dragstarted)
-                .on("drag", dragged)
-                .on("end", dragended));
-
-        node.append("circle")
-            .attr("r", d => 20)
-            .style("fill", d=> d.color);
-
-        node.append("text")
-            .attr("x", 8)
-            .attr("y", "0.31em")
-            .text(d => d.name);
-
-        simulation.on("tick", () => {{
-            link.attr("x1", d => d.source.x)
-                .attr("y1", d => d.source.y)
-                .attr("x2", d => d.target.x)
-                .attr("y2", d => d.target.y);
-
-            node.attr("transform", d => `translate(${{d.x}},${{d.y}})`);
-        }});
+        let simulation = null;
+
+        // Draws the graph, replacing the previous drawing
+        function render(nodes, links) {{
+            if (simulation) simulation.stop();
+            g.selectAll("*").remove();
+
+            nodes.forEach(node => {{
+                node.incomingLinks = links.filter(link => link.target === node.id).length;
+            }});
+
+            simulation = d3.forceSimulation(nodes)
+                .force("link", d3.forceLink(links).id(d => d.id).distance(200))
+                .force("charge", d3.forceManyBody().strength(-500))
+                .force("center", d3.forceCenter(width / 2, height / 2));
+
+            const link = g.selectAll(".link")
+                .data(links)
+                .join("line")
+                .classed("link", true)
+                .attr("stroke-width", d => Math.min(2 + Math.log2(d.weight), 8));
+
+            link.filter(d => d.tooltip)
+                .append("title")
+                .text(d => d.tooltip);
+
+            const node = g.selectAll(".node")
+                .data(nodes)
+                .join("g")
+                .classed("node", true)
+                .call(d3.drag()
+                    .on("start", dragstarted)
+                    .on("drag", dragged)
+                    .on("end", dragended));
+
+            node.append("circle")
+                .attr("r", d => 20)
+                .style("fill", d=> d.color);
+
+            node.append("text")
+                .attr("x", 8)
+                .attr("y", "0.31em")
+                .text(d => d.name);
+
+            // Collapsed namespaces of the drill-down expand on click
+            node.filter(d => d.collapsible)
+                .style("cursor", "pointer")
+                .on("click", (event, d) => expandNode(d.id))
+                .append("title")
+                .text("Click to expand");
+            node.filter(d => d.collapsible)
+                .select("circle")
+                .style("stroke", "dimgray")
+                .style("stroke-width", "3px");
+
+            simulation.on("tick", () => {{
+                link.attr("x1", d => d.source.x)
+                    .attr("y1", d => d.source.y)
+                    .attr("x2", d => d.target.x)
+                    .attr("y2", d => d.target.y);
+
+                node.attr("transform", d => `translate(${{d.x}},${{d.y}})`);
+            }});
+        }}
 
         function dragstarted(event, d) {{
             if (!event.active) simulation.alphaTarget(0.3).restart();
@@ -254,6 +386,11 @@ fn generate_script_code_d3(file: &mut File, nodes: &[Node], node_dependencies: &
             d.fy = null;
         }}
     "#)?;
+    if drill_down.is_some() {
+        writeln!(file, "    redraw();")?;
+    } else {
+        writeln!(file, "    render(nodes, links);")?;
+    }
     writeln!(file, "</script>")?;
 
     Ok(())
@@ -279,28 +416,229 @@ fn generate_body_content_d3(file: &mut File) -> Result<(), Box<dyn std::error::E
     Ok(())
 }
 
-pub fn display_graph_information(nodes: &[Node], node_dependencies: &NodeDependencies) {
-    println!("Found nodes:");
+pub fn display_graph_information(out: &mut dyn Write, nodes: &[Node], node_dependencies: &NodeDependencies) -> std::io::Result<()> {
+    writeln!(out, "Found nodes:")?;
     for (i, project) in nodes.iter().enumerate() {
-        println!("{}: {:?}", i, project);
+        writeln!(out, "{}: {:?}", i, project)?;
     }
 
-    println!("\nNode dependencies:");
+    writeln!(out, "\nNode dependencies:")?;
     for (i, deps) in node_dependencies.iter().enumerate() {
-        let dep_indices = deps.iter().map(|edge_info| edge_info.to.to_string()).collect::<Vec<_>>().join(", ");
-        println!("Project {}: {}", i, dep_indices);
+        let dep_indices = deps.iter()
+            .map(|edge_info| match edge_info.weight {
+                1 => edge_info.to.to_string(),
+                weight => format!("{} (x{})", edge_info.to, weight),
+            })
+            .collect::<Vec<_>>().join(", ");
+        writeln!(out, "Project {}: {}", i, dep_indices)?;
+        for edge_info in deps {
+            for location in &edge_info.locations {
+                writeln!(out, "    -> {}: {}:{}:{} [{}] {}", edge_info.to, location.file, location.line, location.column, location.kind, location.text)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn display_cycles(out: &mut dyn Write, nodes: &[Node], cycles: &[StronglyConnectedComponent]) -> std::io::Result<()> {
+    if cycles.is_empty() {
+        writeln!(out, "No circular dependencies detected.")?;
+        return Ok(());
+    }
+
+    writeln!(out, "Found {} strongly connected component(s) with circular dependencies:", cycles.len())?;
+    for (i, component) in cycles.iter().enumerate() {
+        writeln!(out, "===============================")?;
+        let members = component.members.iter().map(|&m| nodes[m].name.as_str()).collect::<Vec<_>>().join(", ");
+        writeln!(out, "Component {} ({} nodes): {}", i + 1, component.members.len(), members)?;
+        let cycle = component.cycle.iter().chain(component.cycle.first())
+            .map(|&m| nodes[m].name.as_str()).collect::<Vec<_>>().join(" -> ");
+        writeln!(out, "Shortest cycle: {}", cycle)?;
+        writeln!(out, "Edges closing cycles:")?;
+        for &(from, to) in &component.closing_edges {
+            writeln!(out, "    {} -> {}", nodes[from].name, nodes[to].name)?;
+        }
+    }
+    Ok(())
+}
+
+pub fn display_build_order(out: &mut dyn Write, nodes: &[Node], order: &BuildOrder) -> std::io::Result<()> {
+    let step_name = |members: &[usize]| {
+        let names = members.iter().map(|&m| nodes[m].name.as_str()).collect::<Vec<_>>().join(" + ");
+        if order.cycles.iter().any(|component| component.members[0] == members[0]) {
+            format!("{} (cycle)", names)
+        } else {
+            names
+        }
+    };
+
+    writeln!(out, "\nBuild order: {} wave(s)", order.waves.len())?;
+    let mut position = 0;
+    for (index, wave) in order.waves.iter().enumerate() {
+        writeln!(out, "  Wave {}:", index + 1)?;
+        for members in wave {
+            position += 1;
+            writeln!(out, "    {}. {}", position, step_name(members))?;
+        }
+    }
+    let path = order.critical_path.iter().map(|members| step_name(members)).collect::<Vec<_>>().join(" -> ");
+    writeln!(out, "Critical path ({} steps): {}", order.critical_path.len(), path)?;
+
+    if !order.cycles.is_empty() {
+        writeln!(out, "Cycles prevent a valid build order; their nodes are built together as a single step:")?;
+        for component in &order.cycles {
+            let cycle = component.cycle.iter().chain(component.cycle.first())
+                .map(|&m| nodes[m].name.as_str()).collect::<Vec<_>>().join(" -> ");
+            writeln!(out, "    {}", cycle)?;
+        }
+    }
+    Ok(())
+}
+
+pub fn display_impact(out: &mut dyn Write, nodes: &[Node], impacted: &[ImpactedNode]) -> std::io::Result<()> {
+    let dependents = impacted.iter().filter(|node| node.distance > 0).count();
+    writeln!(out, "Changed nodes: {}, affected nodes: {}", impacted.len() - dependents, dependents)?;
+    let mut by_distance: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+    let mut by_layer: BTreeMap<&str, Vec<usize>> = BTreeMap::new();
+    for node in impacted {
+        by_distance.entry(node.distance).or_default().push(node.index);
+        by_layer.entry(nodes[node.index].layer.as_str()).or_default().push(node.index);
+    }
+
+    writeln!(out, "By distance:")?;
+    for (distance, indices) in &by_distance {
+        let heading = if *distance == 0 { "changed".to_string() } else { format!("distance {}", distance) };
+        writeln!(out, "  {} ({}):", heading, indices.len())?;
+        for &index in indices {
+            writeln!(out, "    {} ({})", nodes[index].name, nodes[index].layer)?;
+        }
+    }
+    writeln!(out, "By layer:")?;
+    for (layer, indices) in &by_layer {
+        writeln!(out, "  {} ({}):", layer, indices.len())?;
+        for &index in indices {
+            writeln!(out, "    {}", nodes[index].name)?;
+        }
+    }
+    Ok(())
+}
+
+pub fn display_reference_report(out: &mut dyn Write, nodes: &[Node], report: &ReferenceReport) -> std::io::Result<()> {
+    writeln!(out, "\nUsed without a direct ProjectReference: {}", report.undeclared.len())?;
+    for undeclared in &report.undeclared {
+        let how = if undeclared.transitive { "transitive reference" } else { "not referenced" };
+        writeln!(out, "  {} -> {} ({})", nodes[undeclared.from_project].name, nodes[undeclared.to_project].name, how)?;
+        for &(from, to) in &undeclared.namespaces {
+            writeln!(out, "      {} -> {}", nodes[from].name, nodes[to].name)?;
+        }
+    }
+
+    writeln!(out, "\nUnused ProjectReferences: {}", report.unused.len())?;
+    for unused in &report.unused {
+        writeln!(out, "  {} -> {}", nodes[unused.from].name, nodes[unused.to].name)?;
+        for location in &unused.locations {
+            writeln!(out, "      {}:{}:{} {}", location.file, location.line, location.column, location.text)?;
+        }
+    }
+    Ok(())
+}
+
+/// Lists the lines to delete to remove the unused project references, grouped by file.
+pub fn display_fix_suggestions(out: &mut dyn Write, nodes: &[Node], unused: &[UnusedReference]) -> std::io::Result<()> {
+    writeln!(out, "\nSuggested fixes: delete these lines")?;
+    let mut by_file: BTreeMap<&str, Vec<(&UnusedReference, &SourceLocation)>> = BTreeMap::new();
+    for reference in unused {
+        for location in &reference.locations {
+            by_file.entry(location.file.as_str()).or_default().push((reference, location));
+        }
     }
+    for (file, mut entries) in by_file {
+        entries.sort_by_key(|(_, location)| location.line);
+        writeln!(out, "{}", file)?;
+        for (reference, location) in entries {
+            let range = if location.end_line > location.line { format!("lines {}-{}", location.line, location.end_line) } else { format!("line {}", location.line) };
+            // A reference in Directory.Build.props or an imported file is shared by other projects
+            let shared = if nodes[reference.from].id != file { ", shared with the other projects importing this file" } else { "" };
+            writeln!(out, "  {}: {} ({} -> {}{})", range, location.text, nodes[reference.from].name, nodes[reference.to].name, shared)?;
+        }
+    }
+    Ok(())
+}
+
+pub fn display_check_summary(out: &mut dyn Write, nodes: &[Node], result: &CheckResult) -> std::io::Result<()> {
+    if result.baselined_violations > 0 {
+        writeln!(out, "{} known disallowed dependencies accepted by the baseline.", result.baselined_violations)?;
+    }
+    if !result.fixed_baseline_entries.is_empty() {
+        writeln!(out, "{} baseline entries are fixed and can be removed from the baseline:", result.fixed_baseline_entries.len())?;
+        for entry in &result.fixed_baseline_entries {
+            writeln!(out, "    {} ({}) -> {} ({})", entry.from, entry.from_layer, entry.to, entry.to_layer)?;
+        }
+    }
+
+    if result.passed() {
+        writeln!(out, "Check passed: no disallowed dependencies, cycles, unknown nodes or unstable dependencies.")?;
+        return Ok(());
+    }
+
+    writeln!(out, "Check failed:")?;
+    if !result.violations.is_empty() {
+        writeln!(out, "  Disallowed dependencies: {} (exit code {})", result.violation_count(), EXIT_DISALLOWED_EDGES)?;
+        for ((from_layer, to_layer), edges) in &result.violations {
+            writeln!(out, "    {} -> {}: {}", from_layer, to_layer, edges.len())?;
+            for &(from, to) in edges {
+                writeln!(out, "      {} -> {}", nodes[from].name, nodes[to].name)?;
+            }
+        }
+    }
+    if !result.cycles.is_empty() {
+        writeln!(out, "  Cycles: {} (exit code {})", result.cycles.len(), EXIT_CYCLES)?;
+        for component in &result.cycles {
+            let cycle = component.cycle.iter().chain(component.cycle.first())
+                .map(|&m| nodes[m].name.as_str()).collect::<Vec<_>>().join(" -> ");
+            writeln!(out, "    {}", cycle)?;
+        }
+    }
+    if !result.unknown_nodes.is_empty() {
+        writeln!(out, "  Unknown nodes: {} (exit code {})", result.unknown_nodes.len(), EXIT_UNKNOWN_NODES)?;
+        for &index in &result.unknown_nodes {
+            writeln!(out, "    {}", nodes[index].name)?;
+        }
+    }
+    if !result.unstable_dependencies.is_empty() {
+        writeln!(out, "  Unstable dependencies: {} (exit code {})", result.unstable_dependencies.len(), EXIT_UNSTABLE_DEPENDENCIES)?;
+        for dependency in &result.unstable_dependencies {
+            writeln!(
+                out,
+                "    {} (I={:.2}) -> {} (I={:.2})",
+                nodes[dependency.from].name, dependency.from_instability, nodes[dependency.to].name, dependency.to_instability
+            )?;
+        }
+    }
+    Ok(())
 }
 
 pub fn generate_mermaid_diagram(nodes: &[Node], node_dependencies: &NodeDependencies) {
     println!("```mermaid");
     println!("graph TD;");
-    for (index, project) in nodes.iter().enumerate() {
-        println!("    P{}[\"{}\"]", index + 1, project.name);
+    let (clusters, clustered) = node_clusters(nodes);
+    for (index, project) in nodes.iter().enumerate().filter(|(index, _)| !clustered[*index]) {
+        println!("    P{}[\"{}\"]", index + 1, mermaid_label(&project.name));
+    }
+    for (owner, members) in &clusters {
+        println!("    subgraph C{}[\"{}\"]", owner + 1, mermaid_label(&nodes[*owner].name));
+        for &index in members {
+            println!("        P{}[\"{}\"]", index + 1, mermaid_label(&nodes[index].name));
+        }
+        println!("    end");
     }
     for (index, deps) in node_dependencies.iter().enumerate() {
         for dep in deps {
-            println!("    P{} --> P{}", index + 1, dep.to + 1);
+            if dep.weight > 1 {
+                println!("    P{} -->|{}| P{}", index + 1, dep.weight, dep.to + 1);
+            } else {
+                println!("    P{} --> P{}", index + 1, dep.to + 1);
+            }
         }
     }
     println!("```");
@@ -316,33 +654,171 @@ pub fn generate_graphviz_diagram(
       println!("digraph G {{");
       println!("    node [color=grey, style=filled];");
       println!("    node [fontname=\"Verdana\", size=\"30,30\"];");
-      for (index, node) in nodes.iter().enumerate() {
-          if (toggles.show_recognized_nodes && node.layer != "unknown") || (toggles.show_unrecognized_nodes && node.layer == "unknown") {
-              println!("    P{} [label=\"{}\", style=filled, fillcolor=\"{}\"]", index + 1, node.name, node.color);
-          }
+      for line in graphviz_nodes(nodes, toggles) {
+          println!("{}", line);
       }
       for (index, deps) in node_dependencies.iter().enumerate() {
           for dep in deps {
-              if dep.allowed && toggles.show_valid_dependencies {
-                  println!("    P{} -> P{}", index + 1, dep.to + 1);
-              } else if !dep.allowed && toggles.show_invalid_dependencies {
-                  println!("    P{} -> P{} [color=\"red\" style=dashed penwidth=2]", index + 1, dep.to + 1);
+              let visible = if dep.allowed { toggles.show_valid_dependencies } else { toggles.show_invalid_dependencies };
+              if visible {
+                  println!("    P{} -> P{}{}", index + 1, dep.to + 1, graphviz_edge_attributes(dep, "dashed", false));
               }
           }
       }
 
       // Subgraph for layers
-      println!("\tsubgraph cluster_key {{");
-      println!("\t\tlabel=\"Layer Rules\";");
-      for (index, layer) in layers.iter().enumerate() {
-          println!("    L{} [label=\"{}\", style=filled, fillcolor=\"{}\"]", index + 1, layer.name, layer.color);
+      for line in graphviz_layer_rules(layers, layer_dependencies) {
+          println!("{}", line);
       }
-      for (index, deps) in layer_dependencies.iter().enumerate() {
-          for dep in deps {
-              println!("    L{} -> L{}", index + 1, dep.to + 1);
-          }
-      }
-      println!("\t}}");
 
       println!("}}");
   }
+
+// Nodes whose group is the id of another node (e.g. namespaces of the `csharp:all` analysis,
+// grouped by owning project) are drawn with that node inside a cluster. Returns the clusters as
+// (owner, members including the owner) and which nodes belong to one.
+fn node_clusters(nodes: &[Node]) -> (Vec<(usize, Vec<usize>)>, Vec<bool>) {
+    let index_by_id: HashMap<&str, usize> = nodes.iter().enumerate()
+        .map(|(index, node)| (node.id.as_str(), index))
+        .collect();
+    let mut clusters: Vec<(usize, Vec<usize>)> = Vec::new();
+    let mut clustered = vec![false; nodes.len()];
+    for (index, node) in nodes.iter().enumerate() {
+        let Some(&owner) = node.group.as_deref().and_then(|group| index_by_id.get(group)) else { continue };
+        if owner == index {
+            continue;
+        }
+        match clusters.iter_mut().find(|(cluster_owner, _)| *cluster_owner == owner) {
+            Some((_, members)) => members.push(index),
+            None => clusters.push((owner, vec![owner, index])),
+        }
+        clustered[owner] = true;
+        clustered[index] = true;
+    }
+    (clusters, clustered)
+}
+
+// Node statements of the Graphviz outputs, with clustered nodes in `subgraph cluster_*` blocks
+fn graphviz_nodes(nodes: &[Node], toggles: &Toggles) -> Vec<String> {
+    let visible = |node: &Node| (toggles.show_recognized_nodes && node.layer != "unknown") || (toggles.show_unrecognized_nodes && node.layer == "unknown");
+    let statement = |index: usize, indent: &str, shape: &str| {
+        let node = &nodes[index];
+        format!("{}P{} [label=\"{}\", style=filled, fillcolor=\"{}\"{}]", indent, index + 1, dot_string(&node.name), dot_string(&node.color), shape)
+    };
+    let (clusters, clustered) = node_clusters(nodes);
+
+    let mut lines: Vec<String> = nodes.iter().enumerate()
+        .filter(|(index, node)| !clustered[*index] && visible(node))
+        .map(|(index, _)| statement(index, "    ", ""))
+        .collect();
+    for (owner, members) in clusters {
+        lines.push(format!("    subgraph cluster_P{} {{", owner + 1));
+        lines.push(format!("        label=\"{}\";", dot_string(&nodes[owner].name)));
+        lines.push("        style=\"rounded,dashed\";".to_string());
+        for index in members.into_iter().filter(|&index| visible(&nodes[index])) {
+            let shape = if index == owner { ", shape=box" } else { "" };
+            lines.push(statement(index, "        ", shape));
+        }
+        lines.push("    }".to_string());
+    }
+    lines
+}
+
+// Edge thickness grows with the logarithm of the weight so heavy edges stay readable
+fn penwidth(weight: usize) -> f64 {
+    ((1.0 + (weight.max(1) as f64).log2()).min(6.0) * 10.0).round() / 10.0
+}
+
+// Graphviz attributes of a dependency edge: red with the given style when not allowed, thicker
+// and labeled when the weight is above 1, plus the tooltip for the HTML output
+fn graphviz_edge_attributes(dep: &EdgeInfo, invalid_style: &str, with_tooltip: bool) -> String {
+    let mut attributes = Vec::new();
+    if !dep.allowed {
+        attributes.push(format!("color=\"red\" style={}", invalid_style));
+        attributes.push(format!("penwidth={}", penwidth(dep.weight).max(2.0)));
+    } else if dep.weight > 1 {
+        attributes.push(format!("penwidth={}", penwidth(dep.weight)));
+    }
+    if dep.weight > 1 {
+        attributes.push(format!("label=\"{}\"", dep.weight));
+    }
+    if with_tooltip && (!dep.locations.is_empty() || !dep.merged.is_empty()) {
+        attributes.push(format!("tooltip=\"{}\"", dot_string(&edge_tooltip(dep))));
+    }
+    if attributes.is_empty() { String::new() } else { format!(" [{}]", attributes.join(" ")) }
+}
+
+/// Escapes a value for a double-quoted Graphviz string.
+pub fn dot_string(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+// JavaScript string literal, with "</" escaped so the text cannot close the script element
+fn js_string(value: &str) -> String {
+    serde_json::Value::from(value).to_string().replace("</", "<\\/")
+}
+
+/// Escapes a value for a double-quoted Mermaid label.
+pub fn mermaid_label(value: &str) -> String {
+    value.replace('"', "#quot;")
+}
+
+// Lines of an edge tooltip, in the static outputs and in the drill-down
+const MAX_TOOLTIP_LOCATIONS: usize = 20;
+
+// One line per declaration of the edge (`file:line:column text`), or per node pair for an
+// aggregated edge
+fn edge_tooltip(dep: &EdgeInfo) -> String {
+    let mut lines: Vec<String> = if dep.merged.is_empty() {
+        dep.locations.iter()
+            .map(|location| format!("{}:{}:{} {}", location.file, location.line, location.column, location.text))
+            .collect()
+    } else {
+        dep.merged.clone()
+    };
+    if lines.len() > MAX_TOOLTIP_LOCATIONS {
+        let more = lines.len() - MAX_TOOLTIP_LOCATIONS;
+        lines.truncate(MAX_TOOLTIP_LOCATIONS);
+        lines.push(format!("... and {} more", more));
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::dependencies::test_graph;
+
+    fn node(id: &str, name: &str, group: Option<&str>) -> Node {
+        Node {
+            id: id.to_string(),
+            name: name.to_string(),
+            layer: "core".to_string(),
+            node_type: "project".to_string(),
+            color: "#ffffff".to_string(),
+            group: group.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn graphviz_statements_escape_names() {
+        let toggles = Toggles { show_valid_dependencies: true, show_invalid_dependencies: true, show_recognized_nodes: true, show_unrecognized_nodes: true };
+        let nodes = [node(r"C:\repo\My.csproj", r#"My "App""#, None), node("My.App", r"My\App", Some(r"C:\repo\My.csproj"))];
+        assert_eq!(graphviz_nodes(&nodes, &toggles), [
+            "    subgraph cluster_P1 {",
+            r#"        label="My \"App\"";"#,
+            "        style=\"rounded,dashed\";",
+            r##"        P1 [label="My \"App\"", style=filled, fillcolor="#ffffff", shape=box]"##,
+            r##"        P2 [label="My\\App", style=filled, fillcolor="#ffffff"]"##,
+            "    }",
+        ]);
+        let rules = graphviz_layer_rules(&nodes[..1], &test_graph(1, &[(0, 0)]));
+        assert_eq!(rules[2], r##"    L1 [label="My \"App\"", style=filled, fillcolor="#ffffff"]"##);
+    }
+
+    #[test]
+    fn labels_and_scripts_are_escaped() {
+        assert_eq!(mermaid_label(r#"My "App""#), "My #quot;App#quot;");
+        assert_eq!(js_string("C:\\repo\\it's</script>\n"), r#""C:\\repo\\it's<\/script>\n""#);
+    }
+}